@@ -0,0 +1,31 @@
+// Live updates for the dogs page: see apps/dogs/events.rs
+//
+// Each event is markup with out-of-band swaps (rows to add, replace or delete),
+// so all we do is hand it to htmx.
+
+(function () {
+  const page = document.querySelector("[data-events]");
+  if (!page) {
+    return;
+  }
+
+  let source = null;
+
+  // The server places changes to suit the page's search and sort, which are
+  // in its URL (kept up to date by `table_rows`), so follow it when it changes.
+  function connect() {
+    if (source) {
+      source.close();
+    }
+    const params = new URLSearchParams(location.search);
+    params.set("client", new URL(page.dataset.events, location.href).searchParams.get("client"));
+
+    source = new EventSource("/dogs/events?" + params);
+    source.addEventListener("change", function (event) {
+      htmx.swap("#dogs-body", event.data, { swapStyle: "none" });
+    });
+  }
+
+  connect();
+  document.body.addEventListener("htmx:replacedInHistory", connect);
+})();
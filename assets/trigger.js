@@ -0,0 +1,16 @@
+function handleEvent1(event) {
+  const {value} = event.detail;
+  alert('got event1 with ' + value);
+}
+function handleEvent2(event) {
+  const {value} = event.detail;
+  alert('got event2 with ' + JSON.stringify(value));
+}
+function handleEvent3(event) {
+  const {detail} = event;
+  // detail.elt holds a reference to the element that
+  // triggered the request. JSON.stringify encounters a
+  // circular reference if that is included, so we remove it.
+  delete detail.elt;
+  alert('got event3 with ' + JSON.stringify(detail));
+}
@@ -0,0 +1,148 @@
+use super::{
+    events::{Change, Changes},
+    listing::Listing,
+    no_such_dog, Dog, DogsState, NewDog, SharedState,
+};
+use crate::{
+    extract::Path,
+    mishap::{self, Mishap},
+    validation::Errors,
+};
+use anyhow::anyhow;
+use axum::{
+    extract::{Query, State},
+    http::{header::LOCATION, HeaderMap, StatusCode},
+    middleware,
+    response::{IntoResponse, Response},
+    routing::{any, get},
+    Json, Router,
+};
+use serde_json::json;
+use uuid::Uuid;
+
+/// The dogs as JSON, for scripts and tests.
+///
+/// This is the same `DogDB` as the pages use, and changes made here
+/// are pushed to open pages like any other.
+///
+/// Errors come back as JSON too, including a 404 for anything else under `/api/`
+/// (see `mishap::json_errors`).
+pub fn routes() -> Router<DogsState> {
+    Router::new()
+        .route("/api/dogs", get(list).post(create))
+        .route("/api/dogs/{id}", get(show).put(update).delete(remove))
+        .route("/api/{*path}", any(unknown))
+        .layer(middleware::from_fn(mishap::json_errors))
+}
+
+async fn unknown() -> Mishap {
+    Mishap::not_found("No such API endpoint")
+}
+
+/// `GET /api/dogs`, taking the same `q`, `sort` and `dir` as the table
+async fn list(
+    State(state): State<SharedState>,
+    Query(listing): Query<Listing>,
+) -> Result<Json<Vec<Dog>>, Mishap> {
+    let db = state
+        .read()
+        .map_err(|_| Mishap::internal(anyhow!("Read lock fail")))?;
+
+    Ok(Json(db.dogs(&listing)?))
+}
+
+async fn show(Path(id): Path<Uuid>, State(state): State<SharedState>) -> Result<Json<Dog>, Mishap> {
+    let db = state
+        .read()
+        .map_err(|_| Mishap::internal(anyhow!("Read lock fail")))?;
+
+    let dog = db.find(&id)?.ok_or_else(|| no_such_dog(&id))?;
+    Ok(Json(dog))
+}
+
+/// `POST /api/dogs` with `{"name": ..., "breed": ...}`:
+/// 201 with the new dog and its `Location`, or 422 with what's wrong.
+async fn create(
+    headers: HeaderMap,
+    State(state): State<SharedState>,
+    State(changes): State<Changes>,
+    Json(new): Json<NewDog>,
+) -> Result<Response, Mishap> {
+    let (name, breed) = match new.validate() {
+        Ok(valid) => valid,
+        Err(errors) => return Ok(invalid(errors)),
+    };
+
+    let dog = Dog::new(name, breed);
+
+    let mut db = state
+        .write()
+        .map_err(|_| Mishap::internal(anyhow!("Write lock fail")))?;
+
+    db.insert(dog.clone(), None)?;
+    changes.publish(&headers, Change::Added(dog.clone()));
+
+    let location = format!("/api/dogs/{}", dog.id);
+    Ok((StatusCode::CREATED, [(LOCATION, location)], Json(dog)).into_response())
+}
+
+/// `PUT /api/dogs/{id}` with a name and breed, and optionally the `version` being updated:
+/// 409 (with the current dog) if that's not the latest version.
+async fn update(
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+    State(state): State<SharedState>,
+    State(changes): State<Changes>,
+    Json(new): Json<NewDog>,
+) -> Result<Response, Mishap> {
+    let mut db = state
+        .write()
+        .map_err(|_| Mishap::internal(anyhow!("Write lock fail")))?;
+
+    let mut dog = db.find(&id)?.ok_or_else(|| no_such_dog(&id))?;
+
+    if new.version.is_some_and(|version| version != dog.version) {
+        let body = json!({
+            "error": "This dog has been changed since that version",
+            "current": dog,
+        });
+        return Ok((StatusCode::CONFLICT, Json(body)).into_response());
+    }
+
+    let (name, breed) = match new.validate() {
+        Ok(valid) => valid,
+        Err(errors) => return Ok(invalid(errors)),
+    };
+
+    dog.name = name.to_string();
+    dog.breed = breed.to_string();
+    dog.version += 1;
+
+    db.insert(dog.clone(), None)?;
+    changes.publish(&headers, Change::Updated(dog.clone()));
+
+    Ok(Json(dog).into_response())
+}
+
+/// `DELETE /api/dogs/{id}`: 204, or 404 if there's no such dog
+async fn remove(
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+    State(state): State<SharedState>,
+    State(changes): State<Changes>,
+) -> Result<StatusCode, Mishap> {
+    let mut db = state
+        .write()
+        .map_err(|_| Mishap::internal(anyhow!("Write lock fail")))?;
+
+    let dog = db.delete(id, None)?.ok_or_else(|| no_such_dog(&id))?;
+    changes.publish(&headers, Change::Deleted(dog.id));
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// A 422 listing the problems with each field, e.g. `{"errors": {"name": ["Required"]}}`
+fn invalid(errors: Errors) -> Response {
+    let body = json!({ "errors": errors });
+    (StatusCode::UNPROCESSABLE_ENTITY, Json(body)).into_response()
+}
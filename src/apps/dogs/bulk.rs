@@ -0,0 +1,394 @@
+use super::{
+    events::{Change, Changes},
+    history,
+    listing::Listing,
+    Dog, DogDB, DogSession, NewDog, SharedState,
+};
+use crate::{
+    components::layout::{home_nav, layout},
+    mishap::Mishap,
+};
+use anyhow::{anyhow, Context};
+use axum::{
+    extract::{Multipart, State},
+    http::{
+        header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+        HeaderMap, StatusCode,
+    },
+    response::{IntoResponse, Response},
+    Form, Json,
+};
+use maud::{html, Markup};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+//
+// Export
+//
+
+/// Every dog, oldest first, as a CSV download (with a header row)
+pub async fn export_csv(State(state): State<SharedState>) -> Result<Response, Mishap> {
+    let dogs = all_dogs(&state)?;
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for dog in &dogs {
+        writer.serialize(dog)?;
+    }
+    let csv = writer.into_inner().map_err(|e| anyhow!("{}", e))?;
+
+    let headers = [
+        (CONTENT_TYPE, "text/csv; charset=utf-8"),
+        (CONTENT_DISPOSITION, "attachment; filename=\"dogs.csv\""),
+    ];
+    Ok((headers, csv).into_response())
+}
+
+/// Every dog, oldest first, as a JSON download
+pub async fn export_json(State(state): State<SharedState>) -> Result<Response, Mishap> {
+    let dogs = all_dogs(&state)?;
+
+    let headers = [(CONTENT_DISPOSITION, "attachment; filename=\"dogs.json\"")];
+    Ok((headers, Json(dogs)).into_response())
+}
+
+fn all_dogs(state: &SharedState) -> Result<Vec<Dog>, Mishap> {
+    let db = state
+        .read()
+        .map_err(|_| Mishap::internal(anyhow!("Read lock fail")))?;
+    Ok(db.dogs(&Listing::default())?)
+}
+
+//
+// Import: upload a file, check the preview, then commit.
+//
+
+/// A dog in an import file: the same shape as an export.
+///
+/// Without an `id`, it's a new dog. With one, it replaces that dog
+/// (or adds it with that id, so an export can seed another server).
+/// Given a `version`, the import stops if the dog has changed since.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+struct Row {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    name: String,
+    breed: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<u64>,
+}
+
+/// What importing one row would do
+enum Outcome {
+    Create(Dog),
+    Update(Dog),
+    Invalid(Vec<String>),
+}
+
+struct Planned {
+    /// Counting from 1, not including any header
+    line: usize,
+    row: Row,
+    outcome: Outcome,
+}
+
+/// Work out what each row would do to the database, without changing it.
+fn plan(rows: Vec<Result<Row, String>>, db: &DogDB) -> anyhow::Result<Vec<Planned>> {
+    let mut seen = HashSet::new();
+    let mut planned = Vec::new();
+
+    for (index, row) in rows.into_iter().enumerate() {
+        let line = index + 1;
+        let (row, outcome) = match row {
+            Ok(row) => {
+                let outcome = plan_row(&row, db, &mut seen)?;
+                (row, outcome)
+            }
+            Err(problem) => (Row::default(), Outcome::Invalid(vec![problem])),
+        };
+        planned.push(Planned { line, row, outcome });
+    }
+
+    Ok(planned)
+}
+
+fn plan_row(row: &Row, db: &DogDB, seen: &mut HashSet<Uuid>) -> anyhow::Result<Outcome> {
+    let new = NewDog {
+        name: row.name.clone(),
+        breed: row.breed.clone(),
+        version: None,
+    };
+
+    let mut problems = Vec::new();
+    let valid = new.validate();
+    if let Err(errors) = &valid {
+        for (field, label) in [("name", "Name"), ("breed", "Breed")] {
+            for message in errors.field(field) {
+                problems.push(format!("{}: {}", label, message));
+            }
+        }
+    }
+
+    let id = match row.id.as_deref().map(str::trim).filter(|id| !id.is_empty()) {
+        None => None,
+        Some(id) => match Uuid::parse_str(id) {
+            Ok(id) if !seen.insert(id) => {
+                problems.push("The same id appears earlier in the file".to_string());
+                None
+            }
+            Ok(id) => Some(id),
+            Err(_) => {
+                problems.push(format!("{} is not a valid id", id));
+                None
+            }
+        },
+    };
+
+    let existing = match id {
+        Some(id) => db.find(&id)?,
+        None => None,
+    };
+
+    if let (Some(dog), Some(version)) = (&existing, row.version) {
+        if dog.version != version {
+            problems.push(format!(
+                "This dog is now at version {}, not {}",
+                dog.version, version
+            ));
+        }
+    }
+
+    let (name, breed) = match valid {
+        Ok(valid) if problems.is_empty() => valid,
+        _ => return Ok(Outcome::Invalid(problems)),
+    };
+
+    Ok(match (existing, id) {
+        (Some(mut dog), _) => {
+            dog.name = name.to_string();
+            dog.breed = breed.to_string();
+            dog.version += 1;
+            Outcome::Update(dog)
+        }
+        (None, Some(id)) => Outcome::Create(Dog {
+            id: id.to_string(),
+            ..Dog::new(name, breed)
+        }),
+        (None, None) => Outcome::Create(Dog::new(name, breed)),
+    })
+}
+
+/// Rows from a CSV file (with a header row naming the columns), or a JSON array.
+///
+/// A row that can't be read is an error for that row, not the whole file.
+fn parse(file_name: &str, bytes: &[u8]) -> Result<Vec<Result<Row, String>>, Mishap> {
+    if file_name.to_lowercase().ends_with(".json") {
+        let values: Vec<serde_json::Value> = serde_json::from_slice(bytes)
+            .context("A JSON import should be an array of dogs")
+            .map_err(Mishap::validation)?;
+        Ok(values
+            .into_iter()
+            .map(|value| serde_json::from_value(value).map_err(|e| e.to_string()))
+            .collect())
+    } else {
+        let mut reader = csv::Reader::from_reader(bytes);
+        Ok(reader
+            .deserialize()
+            .map(|row| row.map_err(|e| e.to_string()))
+            .collect())
+    }
+}
+
+/// `GET /dogs/import`
+pub async fn import_page() -> Markup {
+    let body = html! {
+        h1 { "Import dogs" }
+        p {
+            "Upload a CSV file with " code { "name" } " and " code { "breed" } " columns, "
+            "or a JSON array of dogs. "
+            "Include an " code { "id" } " to replace that dog: an export is a good place to start."
+        }
+        p { a href="/dogs" { "Back to the dogs" } }
+
+        form
+            hx-post="/dogs/import/preview"
+            hx-encoding="multipart/form-data"
+            hx-target="#import-preview"
+            hx-disabled-elt="find button" {
+                input type="file" name="file" accept=".csv,.json,text/csv,application/json" required {}
+                div class="buttons" {
+                    button { "Preview" }
+                }
+            }
+
+        div id="import-preview" {}
+    };
+
+    layout("Import dogs", &["/assets/dogs.css"], Some(home_nav()), body)
+}
+
+/// `POST /dogs/import/preview` with the file: what would happen, row by row.
+pub async fn preview(
+    State(state): State<SharedState>,
+    mut multipart: Multipart,
+) -> Result<Markup, Mishap> {
+    let mut upload = None;
+    while let Some(field) = multipart.next_field().await.map_err(invalid_upload)? {
+        if field.name() == Some("file") {
+            let file_name = field.file_name().unwrap_or_default().to_string();
+            let bytes = field.bytes().await.map_err(invalid_upload)?;
+            upload = Some((file_name, bytes));
+        }
+    }
+    let (file_name, bytes) = upload.ok_or_else(|| invalid_upload("No file was uploaded"))?;
+
+    let rows = parse(&file_name, &bytes)?;
+
+    let db = state
+        .read()
+        .map_err(|_| Mishap::internal(anyhow!("Read lock fail")))?;
+
+    Ok(preview_table(&plan(rows, &db)?))
+}
+
+fn invalid_upload(error: impl std::fmt::Display) -> Mishap {
+    Mishap::validation(anyhow!("{}", error))
+}
+
+/// The rows from a preview, ready to commit
+#[derive(Deserialize)]
+pub struct Confirmed {
+    rows: String,
+}
+
+/// `POST /dogs/import` with the rows from a preview: all of them are saved, or none are.
+///
+/// The rows are checked again, as the dogs may have changed since the preview.
+pub async fn commit(
+    headers: HeaderMap,
+    State(state): State<SharedState>,
+    State(changes): State<Changes>,
+    session: DogSession,
+    Form(confirmed): Form<Confirmed>,
+) -> Result<Response, Mishap> {
+    let rows: Vec<Row> = serde_json::from_str(&confirmed.rows).map_err(Mishap::validation)?;
+
+    let mut db = state
+        .write()
+        .map_err(|_| Mishap::internal(anyhow!("Write lock fail")))?;
+
+    let planned = plan(rows.into_iter().map(Ok).collect(), &db)?;
+
+    if planned
+        .iter()
+        .any(|p| matches!(p.outcome, Outcome::Invalid(_)))
+    {
+        let markup = preview_table(&planned);
+        return Ok((StatusCode::UNPROCESSABLE_ENTITY, markup).into_response());
+    }
+
+    let mut created = 0;
+    let mut dogs = Vec::new();
+    let mut published = Vec::new();
+    for p in planned {
+        match p.outcome {
+            Outcome::Create(dog) => {
+                created += 1;
+                dogs.push(dog.clone());
+                published.push(Change::Added(dog));
+            }
+            Outcome::Update(dog) => {
+                dogs.push(dog.clone());
+                published.push(Change::Updated(dog));
+            }
+            Outcome::Invalid(_) => {}
+        }
+    }
+    let updated = dogs.len() - created;
+
+    db.insert_all(dogs, history::who(&session))?;
+    for change in published {
+        changes.publish(&headers, change);
+    }
+
+    Ok(html! {
+        p {
+            strong { "Imported: " }
+            (created) " added, " (updated) " updated. "
+            a href="/dogs" { "See all the dogs" }
+        }
+    }
+    .into_response())
+}
+
+fn preview_table(planned: &[Planned]) -> Markup {
+    let problems = planned
+        .iter()
+        .filter(|p| matches!(p.outcome, Outcome::Invalid(_)))
+        .count();
+
+    // Only the rows which passed, to be checked again on commit.
+    // Rows to update carry the version we saw, so we notice if it changes in between.
+    let rows: Vec<Row> = planned
+        .iter()
+        .filter_map(|p| match &p.outcome {
+            Outcome::Create(_) => Some(p.row.clone()),
+            // (The planned dog is the next version)
+            Outcome::Update(dog) => Some(Row {
+                version: Some(dog.version - 1),
+                ..p.row.clone()
+            }),
+            Outcome::Invalid(_) => None,
+        })
+        .collect();
+    let rows = serde_json::to_string(&rows).unwrap_or_default();
+
+    html! {
+        @if planned.is_empty() {
+            p { "There are no dogs in that file." }
+        } @else {
+            table class="import" {
+                thead {
+                    tr { th { "Row" } th { "Action" } th { "Name" } th { "Breed" } th { "Problems" } }
+                }
+                tbody {
+                    @for p in planned {
+                        @let (action, class) = match p.outcome {
+                            Outcome::Create(_) => ("Add", None),
+                            Outcome::Update(_) => ("Update", None),
+                            Outcome::Invalid(_) => ("Skip", Some("invalid")),
+                        };
+                        tr class=[class] {
+                            td { (p.line) }
+                            td { (action) }
+                            td { (p.row.name) }
+                            td { (p.row.breed) }
+                            td {
+                                @if let Outcome::Invalid(problems) = &p.outcome {
+                                    @for problem in problems {
+                                        div class="error" { (problem) }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            @if problems > 0 {
+                p {
+                    (problems) " of " (planned.len()) " rows have problems. "
+                    "Nothing has been imported: correct the file and preview it again."
+                }
+            } @else {
+                form hx-post="/dogs/import" hx-target="#import-preview" hx-disabled-elt="find button" {
+                    input type="hidden" name="rows" value=(rows) {}
+                    div class="buttons" {
+                        button { "Import " (planned.len()) " dogs" }
+                    }
+                }
+            }
+        }
+    }
+}
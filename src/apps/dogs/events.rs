@@ -0,0 +1,132 @@
+use super::{listing::Listing, placed_row, Dog, DogDB, SharedState};
+use crate::shutdown;
+use anyhow::anyhow;
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures_util::{stream, Stream, StreamExt};
+use maud::{html, Markup};
+use std::convert::Infallible;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// Sent with every htmx request from a dogs page (see `page`),
+/// so a page isn't told about changes it made itself.
+pub const CLIENT_HEADER: &str = "X-Dogs-Client";
+
+/// Something that happened to a dog, for the other pages to show.
+#[derive(Clone, Debug)]
+pub enum Change {
+    Added(Dog),
+    Updated(Dog),
+    Deleted(String),
+}
+
+#[derive(Clone, Debug)]
+struct Published {
+    /// The page that made the change, if we know it
+    origin: Option<String>,
+    change: Change,
+}
+
+/// Where changes are published, for every open `/dogs/events` stream.
+#[derive(Clone)]
+pub struct Changes(broadcast::Sender<Published>);
+
+impl Changes {
+    pub fn new() -> Changes {
+        // Only a slow reader would fall this far behind
+        let (sender, _) = broadcast::channel(100);
+        Changes(sender)
+    }
+
+    /// Tell every page, other than the one making the request, about a change.
+    pub fn publish(&self, headers: &HeaderMap, change: Change) {
+        let origin = headers
+            .get(CLIENT_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+
+        // An error only means no one is listening
+        let _ = self.0.send(Published { origin, change });
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct Client {
+    client: Option<String>,
+}
+
+/// A stream of out-of-band swaps, one `change` event per change,
+/// for `assets/dogs.js` to hand to htmx.
+///
+/// The query string also has the page's listing (`q`, `sort` and `dir`),
+/// so it's only sent the dogs it shows, in the right places.
+///
+/// The stream ends on shutdown, so it doesn't hold up the drain.
+pub async fn events(
+    Query(Client { client }): Query<Client>,
+    Query(listing): Query<Listing>,
+    State(state): State<SharedState>,
+    State(changes): State<Changes>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let receiver = changes.0.subscribe();
+
+    let published = stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(published) => return Some((published, receiver)),
+                Err(RecvError::Lagged(missed)) => {
+                    tracing::warn!("dogs events stream missed {} changes", missed)
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    let events = published
+        .filter(move |published| {
+            let own = client.is_some() && published.origin == client;
+            std::future::ready(!own)
+        })
+        .filter_map(move |published| {
+            let markup = state
+                .read()
+                .map_err(|_| anyhow!("Read lock fail"))
+                .and_then(|db| swap(&published.change, &listing, &db));
+
+            let event = match markup {
+                // Nothing this page shows
+                Ok(markup) if markup.0.is_empty() => None,
+                Ok(markup) => Some(Ok(Event::default()
+                    .event("change")
+                    .data(markup.into_string()))),
+                Err(e) => {
+                    tracing::error!("Unable to send a dogs change: {:#}", e);
+                    None
+                }
+            };
+            std::future::ready(event)
+        })
+        .take_until(shutdown::signal());
+
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+/// The change as markup to swap into a page listing the dogs with `listing`
+/// (see `placed_row`). A row being edited inline is left alone.
+fn swap(change: &Change, listing: &Listing, db: &DogDB) -> anyhow::Result<Markup> {
+    let dog = match change {
+        // Nothing this page shows
+        Change::Added(dog) if !listing.matches(dog) => return Ok(html! {}),
+        Change::Added(dog) | Change::Updated(dog) => dog,
+        Change::Deleted(id) => {
+            let row_id = format!("row-{}", id);
+            return Ok(html! { tr id=(row_id) hx-swap-oob="delete" {} });
+        }
+    };
+
+    let body = format!("#dogs-body:not(:has(#row-{}.editing))", dog.id);
+    placed_row(dog, listing, db, &body)
+}
@@ -0,0 +1,113 @@
+use super::{Dog, DogSession, SharedState};
+use crate::{
+    components::layout::{home_nav, layout},
+    extract::Path,
+    mishap::Mishap,
+};
+use anyhow::anyhow;
+use axum::extract::State;
+use maud::{html, Markup};
+use std::time::SystemTime;
+use uuid::Uuid;
+
+/// One change to a dog, as recorded by `DogDB`.
+///
+/// A dog that didn't exist before was added; one that doesn't exist after was deleted.
+#[derive(Clone, Debug)]
+pub struct Entry {
+    pub dog_id: String,
+    pub at: SystemTime,
+
+    /// Who made the change, or None for the API (which has no sessions)
+    pub session: Option<String>,
+
+    pub before: Option<Dog>,
+    pub after: Option<Dog>,
+}
+
+impl Entry {
+    /// A change made just now
+    pub fn new(session: Option<String>, before: Option<Dog>, after: Option<Dog>) -> Entry {
+        let dog_id = match (&before, &after) {
+            (_, Some(dog)) | (Some(dog), None) => dog.id.clone(),
+            (None, None) => String::new(),
+        };
+        Entry {
+            dog_id,
+            at: SystemTime::now(),
+            session,
+            before,
+            after,
+        }
+    }
+}
+
+/// The session making a change, to record against it
+pub fn who(session: &DogSession) -> Option<String> {
+    Some(session.get_session_id().to_string())
+}
+
+/// `GET /dogs/{id}/history`: every change to a dog, newest first.
+/// (Still there after the dog is deleted.)
+pub async fn history(
+    Path(id): Path<Uuid>,
+    State(state): State<SharedState>,
+) -> Result<Markup, Mishap> {
+    let db = state
+        .read()
+        .map_err(|_| Mishap::internal(anyhow!("Read lock fail")))?;
+
+    let mut entries = db.history(&id)?;
+    entries.reverse();
+
+    Ok(html! {
+        @if entries.is_empty() {
+            p { "No changes recorded." }
+        } @else {
+            ol class="history" {
+                @for entry in &entries {
+                    li { (render(entry)) }
+                }
+            }
+        }
+    })
+}
+
+/// For when the history is loaded without htmx.
+pub fn history_page(history: Markup) -> Markup {
+    let body = html! {
+        h1 { "Dog history" }
+        (history)
+        p { a href="/dogs" { "Back to the dogs" } }
+    };
+
+    layout("Dog history", &["/assets/dogs.css"], Some(home_nav()), body)
+}
+
+fn render(entry: &Entry) -> Markup {
+    let at = humantime::format_rfc3339_seconds(entry.at).to_string();
+
+    // Enough of the session id to tell people apart
+    let by = match &entry.session {
+        Some(session) => format!("session {}", session.chars().take(8).collect::<String>()),
+        None => "the API".to_string(),
+    };
+
+    html! {
+        time datetime=(at) { (at) } " by " (by) ": "
+        @match (&entry.before, &entry.after) {
+            (None, Some(after)) => { "added " (after.name) " (" (after.breed) ")" },
+            (Some(before), None) => { "deleted " (before.name) " (" (before.breed) ")" },
+            (Some(before), Some(after)) => {
+                "updated"
+                @if before.name != after.name {
+                    " name " del { (before.name) } " → " ins { (after.name) }
+                }
+                @if before.breed != after.breed {
+                    " breed " del { (before.breed) } " → " ins { (after.breed) }
+                }
+            },
+            (None, None) => {},
+        }
+    }
+}
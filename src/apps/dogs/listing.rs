@@ -0,0 +1,336 @@
+use super::Dog;
+use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, ops::Range};
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+use uuid::Uuid;
+
+/// Which column the table is sorted by.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortKey {
+    /// Oldest first: our ids are UUIDv7s, which start with their creation time
+    #[default]
+    Created,
+    Name,
+    Breed,
+}
+
+impl SortKey {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SortKey::Created => "created",
+            SortKey::Name => "name",
+            SortKey::Breed => "breed",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl Direction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Direction::Asc => "asc",
+            Direction::Desc => "desc",
+        }
+    }
+
+    /// For the `aria-sort` attribute on a header
+    pub fn aria(self) -> &'static str {
+        match self {
+            Direction::Asc => "ascending",
+            Direction::Desc => "descending",
+        }
+    }
+}
+
+/// How to list the dogs, from the query string of `/dogs` and `/dogs/table-rows`.
+///
+/// e.g. `?q=pointer&sort=name&dir=desc`. Anything missing takes the default:
+/// every dog, oldest first.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Listing {
+    /// Only dogs whose name or breed contains this
+    #[serde(skip_serializing_if = "is_blank")]
+    pub q: String,
+
+    #[serde(skip_serializing_if = "is_default")]
+    pub sort: SortKey,
+
+    #[serde(skip_serializing_if = "is_default")]
+    pub dir: Direction,
+}
+
+impl Listing {
+    /// The listing in a URL's query string, if it has one.
+    pub fn from_url(url: &str) -> Option<Listing> {
+        let (_, query) = url.split_once('?')?;
+        serde_urlencoded::from_str(query).ok()
+    }
+
+    /// Does the search match this dog?
+    pub fn matches(&self, dog: &Dog) -> bool {
+        is_blank(&self.q) || contains(&dog.name, &self.q) || contains(&dog.breed, &self.q)
+    }
+
+    /// Put the dogs in order.
+    ///
+    /// Names and breeds are compared ignoring case, with ties in creation order,
+    /// so the table doesn't shuffle between reloads.
+    pub fn sort(&self, dogs: &mut [Dog]) {
+        dogs.sort_by(|a, b| self.compare(a, b));
+    }
+
+    /// Which of two dogs comes first. Only equal for the same dog.
+    pub fn compare(&self, a: &Dog, b: &Dog) -> Ordering {
+        self.order(self.key(a).cmp(&self.key(b)).then_with(|| a.id.cmp(&b.id)))
+    }
+
+    /// What the listing orders a dog by, before its id: blank in creation order.
+    pub fn key(&self, dog: &Dog) -> String {
+        match self.sort {
+            SortKey::Created => String::new(),
+            SortKey::Name => sort_key(&dog.name),
+            SortKey::Breed => sort_key(&dog.breed),
+        }
+    }
+
+    /// Where this dog is in the listing, for a page to start after.
+    pub fn after(&self, dog: &Dog) -> After {
+        After {
+            key: self.key(dog),
+            id: dog.id.clone(),
+        }
+    }
+
+    /// Does this dog come after that position in the listing?
+    pub fn is_after(&self, dog: &Dog, after: &After) -> bool {
+        let ordering =
+            (self.key(dog).as_str(), dog.id.as_str()).cmp(&(after.key.as_str(), after.id.as_str()));
+        self.order(ordering).is_gt()
+    }
+
+    fn order(&self, ascending: Ordering) -> Ordering {
+        match self.dir {
+            Direction::Asc => ascending,
+            Direction::Desc => ascending.reverse(),
+        }
+    }
+
+    /// What clicking the header for `key` does next:
+    /// sort ascending, then descending, then back to the default.
+    /// The search is kept.
+    pub fn toggle(&self, key: SortKey) -> Listing {
+        let (sort, dir) = match (self.sort == key, self.dir) {
+            (false, _) => (key, Direction::Asc),
+            (true, Direction::Asc) => (key, Direction::Desc),
+            (true, Direction::Desc) => (SortKey::default(), Direction::default()),
+        };
+        Listing {
+            sort,
+            dir,
+            ..self.clone()
+        }
+    }
+
+    /// `path` with this listing as its query string (leaving out any defaults).
+    pub fn url(&self, path: &str) -> String {
+        let query = serde_urlencoded::to_string(self).unwrap_or_default();
+        if query.is_empty() {
+            path.to_string()
+        } else {
+            format!("{}?{}", path, query)
+        }
+    }
+
+    /// `path` for the page of this listing following `after`.
+    pub fn next_url(&self, path: &str, after: &After) -> String {
+        let cursor = Cursor {
+            after: Uuid::parse_str(&after.id).ok(),
+            after_key: Some(after.key.clone()).filter(|key| !key.is_empty()),
+        };
+        let url = self.url(path);
+        let separator = if url.contains('?') { '&' } else { '?' };
+        let query = serde_urlencoded::to_string(cursor).unwrap_or_default();
+        format!("{}{}{}", url, separator, query)
+    }
+}
+
+/// A position in a listing: just after a dog, as it was when it was shown.
+///
+/// Renaming the dog (or deleting it) doesn't move the position,
+/// so the next page carries on from where the last one stopped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct After {
+    /// See `Listing::key`
+    pub key: String,
+    pub id: String,
+}
+
+/// Where a page of the listing starts, from the query string of `/dogs/table-rows`.
+///
+/// e.g. `?after=<id>&after_key=oscar` for the dogs following Oscar when sorting by name.
+/// (Not part of `Listing`, so it's left out of the URLs for sorting and searching.)
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Cursor {
+    /// The last dog shown: without one, the first page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<Uuid>,
+
+    /// What the listing sorted that dog by (not needed in creation order)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after_key: Option<String>,
+}
+
+impl Cursor {
+    pub fn position(self, listing: &Listing) -> anyhow::Result<Option<After>> {
+        let Some(id) = self.after else {
+            return Ok(None);
+        };
+        let key = match (listing.sort, self.after_key) {
+            (SortKey::Created, _) => String::new(),
+            (_, Some(key)) => key,
+            (sort, None) => anyhow::bail!("after_key is needed to sort by {}", sort.as_str()),
+        };
+        Ok(Some(After {
+            key,
+            id: id.to_string(),
+        }))
+    }
+}
+
+fn is_blank(q: &str) -> bool {
+    q.trim().is_empty()
+}
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+/// Names and breeds are sorted ignoring case
+pub fn sort_key(text: &str) -> String {
+    text.to_lowercase()
+}
+
+/// The text as search sees it, with each character folded (see `fold`):
+/// a search matches if its folded text is in here.
+pub fn folded(text: &str) -> String {
+    text.chars().flat_map(fold).collect()
+}
+
+/// The folded search, or None if there's nothing to search for
+pub fn folded_search(q: &str) -> Option<String> {
+    Some(folded(q.trim())).filter(|q| !q.is_empty())
+}
+
+fn contains(text: &str, q: &str) -> bool {
+    !find(text, q).is_empty()
+}
+
+/// A character as search compares it: lower case, with accents removed.
+/// (So "É" becomes "e", but it may also become nothing, or several characters.)
+fn fold(c: char) -> impl Iterator<Item = char> {
+    c.nfd()
+        .flat_map(char::to_lowercase)
+        .filter(|c| !is_combining_mark(*c))
+}
+
+/// Where `q` appears in `text`, ignoring case and accents,
+/// as byte ranges of `text` (for highlighting).
+pub fn find(text: &str, q: &str) -> Vec<Range<usize>> {
+    let needle: Vec<char> = q.trim().chars().flat_map(fold).collect();
+    if needle.is_empty() {
+        return Vec::new();
+    }
+
+    // Each folded character, with the part of `text` it came from
+    let mut folded: Vec<(char, Range<usize>)> = Vec::new();
+    for (start, c) in text.char_indices() {
+        let end = start + c.len_utf8();
+        let mut chars = fold(c).peekable();
+        if chars.peek().is_none() {
+            // A lone combining accent belongs to the character before it
+            if let Some((_, range)) = folded.last_mut() {
+                range.end = end;
+            }
+        }
+        folded.extend(chars.map(|f| (f, start..end)));
+    }
+
+    let mut found: Vec<Range<usize>> = Vec::new();
+    let mut i = 0;
+    while i + needle.len() <= folded.len() {
+        let window = &folded[i..i + needle.len()];
+        if window.iter().map(|(c, _)| *c).eq(needle.iter().copied()) {
+            let start = window[0].1.start;
+            let end = window[needle.len() - 1].1.end;
+            match found.last_mut() {
+                // Matches can share a character that folded into several
+                Some(last) if last.end > start => last.end = end,
+                _ => found.push(start..end),
+            }
+            i += needle.len();
+        } else {
+            i += 1;
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::find;
+
+    #[test]
+    fn ignores_case() {
+        assert_eq!(find("Comet", "COM"), vec![0..3]);
+        assert_eq!(find("WHIPPET", "pet"), vec![4..7]);
+    }
+
+    #[test]
+    fn ignores_accents() {
+        // "é" is two bytes
+        assert_eq!(find("Bichon Frisé", "frise"), vec![7..13]);
+        assert_eq!(find("Bichon Frise", "FRISÉ"), vec![7..12]);
+    }
+
+    #[test]
+    fn keeps_a_separate_accent_with_its_letter() {
+        // "e" followed by a combining acute accent
+        assert_eq!(find("Cafe\u{301} au lait", "cafe"), vec![0..6]);
+    }
+
+    #[test]
+    fn finds_every_match() {
+        assert_eq!(find("abcabc", "abc"), vec![0..3, 3..6]);
+        assert_eq!(find("aaaa", "aa"), vec![0..2, 2..4]);
+        assert_eq!(find("aaa", "aa"), vec![0..2]);
+    }
+
+    #[test]
+    fn merges_matches_sharing_a_character() {
+        // Each syllable folds into two jamo, and each match ends in the middle of one
+        assert_eq!(find("가가가", "\u{1161}\u{1100}"), vec![0..9]);
+        assert_eq!(find("가나", "\u{1161}"), vec![0..3, 3..6]);
+    }
+
+    #[test]
+    fn nothing_for_a_blank_search() {
+        assert!(find("Comet", "").is_empty());
+        assert!(find("Comet", "   ").is_empty());
+        assert!(find("", "comet").is_empty());
+    }
+
+    #[test]
+    fn trims_the_search() {
+        assert_eq!(find("Comet", " met "), vec![2..5]);
+    }
+}
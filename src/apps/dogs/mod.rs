@@ -1,30 +1,62 @@
-use crate::mishap::Mishap;
+use crate::{
+    apps::App,
+    components::layout::{home_nav, layout},
+    extract::Path,
+    htmx::{full_page_fallback, FullPage, HxRequest, HxResponse},
+    mishap::Mishap,
+    validation::{Errors, Rule},
+};
 use anyhow::anyhow;
 use axum::response::IntoResponse;
 use axum::{
-    body::Body,
-    extract::{Path, State},
+    extract::{FromRef, Query, State},
+    http::HeaderMap,
     http::StatusCode,
-    response::{Html, Response},
-    routing::{get, put},
+    middleware::from_fn_with_state,
+    response::{Redirect, Response},
+    routing::{get, post, put},
     Form, Router,
 };
+use axum_session::{Session, SessionConfig, SessionLayer, SessionNullPool, SessionStore};
 use maud::{html, Markup};
+use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
+    path::PathBuf,
     sync::{Arc, RwLock},
+    time::{Duration, Instant},
 };
+use tokio::task::JoinHandle;
 use uuid::Uuid;
 
 //
-// Datastructure and a fake in-memory database
+// Datastructure and a database of dogs
 //
 
-#[derive(Clone, Debug)]
+mod api;
+mod bulk;
+mod events;
+mod history;
+mod listing;
+mod repository;
+mod sqlite;
+#[cfg(test)]
+mod tests;
+
+use events::{Change, Changes, CLIENT_HEADER};
+use history::Entry;
+use listing::{find, After, Cursor, Listing, SortKey};
+use repository::{DogRepository, MemoryRepository};
+use sqlite::SqliteRepository;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 struct Dog {
     id: String,
     name: String,
     breed: String,
+
+    /// Goes up by one with each update, so we can spot edits made to an old copy
+    version: u64,
 }
 
 impl Dog {
@@ -34,218 +66,824 @@ impl Dog {
             id: id.to_string(),
             name: name.to_string(),
             breed: breed.to_string(),
+            version: 1,
         }
     }
 }
 
+/// Where to keep the dogs.
+pub enum Storage {
+    /// Lost on restart
+    Memory,
+    /// A local SQLite file, created and migrated on startup
+    Sqlite(PathBuf),
+}
+
+/// How long a deleted dog can be brought back
+const UNDO_WINDOW: Duration = Duration::from_secs(10);
+
+/// A deleted dog, kept for a while in case the delete is undone.
+///
+/// These only live in memory: after a restart, deletes are final.
+struct Tombstone {
+    dog: Dog,
+    expires: Instant,
+}
+
+/// A dog to start with (see `fixtures`).
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Seed {
+    name: String,
+    breed: String,
+}
+
+/// The dogs a new database starts with, oldest first.
+#[derive(Debug, Deserialize)]
+#[serde(transparent)]
+pub struct Seeds(Vec<Seed>);
+
+impl Default for Seeds {
+    fn default() -> Seeds {
+        let seed = |name: &str, breed: &str| Seed {
+            name: name.to_string(),
+            breed: breed.to_string(),
+        };
+        Seeds(vec![
+            seed("Comet", "Whippet"),
+            seed("Oscar", "German Shorthaired Pointer"),
+        ])
+    }
+}
+
+impl Seeds {
+    /// New dogs, checked like any others
+    fn dogs(&self) -> anyhow::Result<Vec<Dog>> {
+        let mut dogs = Vec::new();
+        for (index, seed) in self.0.iter().enumerate() {
+            let new = NewDog {
+                name: seed.name.clone(),
+                breed: seed.breed.clone(),
+                version: None,
+            };
+            let (name, breed) = new
+                .validate()
+                .map_err(|errors| anyhow!("Fixture dog {} is not valid: {}", index + 1, errors))?;
+            dogs.push(Dog::new(name, breed));
+        }
+        Ok(dogs)
+    }
+}
+
 struct DogDB {
     // Database of dogs:
-    dogs: HashMap<String, Dog>,
+    repo: Box<dyn DogRepository>,
 
-    // Holds id of currently selected dog
-    // I'm following the example: I do not like this state management
-    // (regardless that I've implemented it as global state, single user!)
-    selected_id: Option<Uuid>,
+    // Recently deleted dogs, by id
+    tombstones: HashMap<String, Tombstone>,
 }
 
 impl DogDB {
-    fn new() -> DogDB {
-        let comet = Dog::new("Comet", "Whippet");
-        let oscar = Dog::new("Oscar", "German Shorthaired Pointer");
+    fn open(storage: &Storage, seeds: &Seeds) -> anyhow::Result<DogDB> {
+        let (repo, fresh): (Box<dyn DogRepository>, bool) = match storage {
+            Storage::Memory => (Box::new(MemoryRepository::default()), true),
+            Storage::Sqlite(path) => {
+                let repo = SqliteRepository::open(path)?;
+                let fresh = repo.fresh;
+                (Box::new(repo), fresh)
+            }
+        };
 
-        DogDB {
-            dogs: HashMap::from([(comet.id.clone(), comet), (oscar.id.clone(), oscar)]),
-            selected_id: None,
+        let mut db = DogDB {
+            repo,
+            tombstones: HashMap::new(),
+        };
+
+        // Only seed a brand new database, so we don't resurrect dogs someone deleted
+        if fresh {
+            // (Nobody made these changes, so they're not in the history)
+            db.repo.save_all(seeds.dogs()?, Vec::new())?;
         }
+
+        Ok(db)
+    }
+
+    fn dogs(&self, listing: &Listing) -> anyhow::Result<Vec<Dog>> {
+        let mut dogs = self.repo.all()?;
+        dogs.retain(|dog| listing.matches(dog));
+        listing.sort(&mut dogs);
+        Ok(dogs)
     }
 
-    fn dogs(&self) -> Vec<Dog> {
-        self.dogs.values().cloned().collect()
+    /// Up to `limit` dogs, in the listing's order, following `after` (or from the start).
+    /// Also says whether there are more to come.
+    fn page(
+        &self,
+        listing: &Listing,
+        after: Option<&After>,
+        limit: usize,
+    ) -> anyhow::Result<(Vec<Dog>, bool)> {
+        // One extra, to see if there's another page
+        let mut dogs = self.repo.page(listing, after, limit + 1)?;
+        let more = dogs.len() > limit;
+        dogs.truncate(limit);
+        Ok((dogs, more))
     }
 
-    fn find(&self, id: &Uuid) -> Option<&Dog> {
-        self.dogs.values().find(|dog| dog.id == id.to_string())
+    fn find(&self, id: &Uuid) -> anyhow::Result<Option<Dog>> {
+        self.repo.find(id)
     }
 
-    fn select(&mut self, id: &Uuid) {
-        self.selected_id = Some(*id);
+    /// Changes made to a dog, oldest first, including any made before it was deleted
+    fn history(&self, id: &Uuid) -> anyhow::Result<Vec<Entry>> {
+        self.repo.history(id)
     }
 
-    fn deselect(&mut self) {
-        self.selected_id = None;
+    /// Insert or replace a dog, recording the change against `session`
+    /// (None for changes which don't come from a page).
+    fn insert(&mut self, dog: Dog, session: Option<String>) -> anyhow::Result<()> {
+        let entry = self.entry(&dog, session)?;
+        self.repo.save(dog, entry)
     }
 
-    fn insert(&mut self, dog: Dog) {
-        self.deselect();
-        self.dogs.insert(dog.id.clone(), dog);
+    /// Insert or replace all of the dogs, or none of them
+    fn insert_all(&mut self, dogs: Vec<Dog>, session: Option<String>) -> anyhow::Result<()> {
+        let entries = dogs
+            .iter()
+            .map(|dog| self.entry(dog, session.clone()))
+            .collect::<anyhow::Result<_>>()?;
+        self.repo.save_all(dogs, entries)
     }
 
-    fn delete(&mut self, id: Uuid) {
-        self.deselect();
-        let _before_delete = self.dogs.remove(&id.to_string());
+    /// The history entry for saving `dog`: it's new, or replaces what's stored now
+    fn entry(&self, dog: &Dog, session: Option<String>) -> anyhow::Result<Entry> {
+        let before = self.repo.find(&Uuid::parse_str(&dog.id)?)?;
+        Ok(Entry::new(session, before, Some(dog.clone())))
+    }
+
+    fn flush(&self) -> anyhow::Result<()> {
+        self.repo.flush()
+    }
+
+    /// The deleted dog, or None if there was no such dog.
+    /// It can be restored until `UNDO_WINDOW` has passed.
+    fn delete(&mut self, id: Uuid, session: Option<String>) -> anyhow::Result<Option<Dog>> {
+        // Only recorded if there's a dog to delete
+        let before = self.repo.find(&id)?;
+        let entry = Entry::new(session, before, None);
+
+        let deleted = self.repo.delete(&id, entry)?;
+        if let Some(dog) = &deleted {
+            let tombstone = Tombstone {
+                dog: dog.clone(),
+                expires: Instant::now() + UNDO_WINDOW,
+            };
+            self.tombstones.insert(dog.id.clone(), tombstone);
+        }
+        Ok(deleted)
+    }
+
+    /// Undo a delete, giving back the dog, or None if it's too late
+    fn restore(&mut self, id: Uuid, session: Option<String>) -> anyhow::Result<Option<Dog>> {
+        match self.tombstones.remove(&id.to_string()) {
+            Some(tombstone) if tombstone.expires > Instant::now() => {
+                self.insert(tombstone.dog.clone(), session)?;
+                Ok(Some(tombstone.dog))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Forget deleted dogs that can no longer be restored, returning how many
+    fn purge(&mut self) -> usize {
+        let now = Instant::now();
+        let before = self.tombstones.len();
+        self.tombstones
+            .retain(|_, tombstone| tombstone.expires > now);
+        before - self.tombstones.len()
     }
 }
 
 type SharedState = Arc<RwLock<DogDB>>;
 
+/// What the routes share: handlers take whichever part they need.
+#[derive(Clone)]
+struct DogsState {
+    db: SharedState,
+    changes: Changes,
+}
+
+impl FromRef<DogsState> for SharedState {
+    fn from_ref(state: &DogsState) -> SharedState {
+        state.db.clone()
+    }
+}
+
+impl FromRef<DogsState> for Changes {
+    fn from_ref(state: &DogsState) -> Changes {
+        state.changes.clone()
+    }
+}
+
+//
+// Each visitor has their own selection: the dog being edited.
+// Following the example this is server-side state, but it's per-session,
+// so one person clicking "Edit" doesn't swap the form out from under another.
+//
+
+type DogSession = Session<SessionNullPool>;
+
+const SELECTED_ID: &str = "selected_dog_id";
+
+fn selected_id(session: &DogSession) -> Option<Uuid> {
+    session.get::<Uuid>(SELECTED_ID)
+}
+
+fn select(session: &DogSession, id: &Uuid) {
+    session.set(SELECTED_ID, id);
+}
+
+fn deselect(session: &DogSession) {
+    session.remove(SELECTED_ID);
+}
+
+/// How many rows `/dogs/table-rows` sends at a time
+const ROWS_PER_PAGE: usize = 25;
+
+/// Fired at the client whenever the selection changes, so the form re-renders
+const SELECTION_CHANGE: &str = "selection-change";
+
 //
 // Forms, routes and route handers
 //
 
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, Default)]
 struct NewDog {
     name: String,
     breed: String,
+
+    /// The version of the dog being edited (not used when adding a dog).
+    /// Left out, an update replaces whatever version is there.
+    #[serde(default)]
+    version: Option<u64>,
 }
 
-pub fn routes() -> Router {
-    let db = DogDB::new();
+impl NewDog {
+    /// What the form shows when editing a dog
+    fn from_dog(dog: &Dog) -> NewDog {
+        NewDog {
+            name: dog.name.clone(),
+            breed: dog.breed.clone(),
+            version: Some(dog.version),
+        }
+    }
+
+    /// The trimmed name and breed, or what's wrong with them
+    fn validate(&self) -> Result<(&str, &str), Errors> {
+        // Enough for "Mr. O'Reilly-Smith 2nd" and "Bichon Frisé"
+        let characters = Rule::Chars(
+            |c| c.is_alphanumeric() || " '-.".contains(c),
+            "letters, numbers, spaces, apostrophes, hyphens and full stops",
+        );
+
+        let mut errors = Errors::default();
+        errors.check(
+            "name",
+            &self.name,
+            &[Rule::Required, Rule::MaxChars(50), characters],
+        );
+        errors.check(
+            "breed",
+            &self.breed,
+            &[Rule::Required, Rule::MaxChars(100), characters],
+        );
 
-    Router::new()
-        .route("/dogs", get(index).post(add_dog))
-        .route("/dogs/form", get(stateful_form))
-        .route("/dogs/select/{id}", put(select_dog))
-        .route("/dogs/deselect", put(deselect))
-        .route("/dogs/table-rows", get(table_rows))
-        .route("/dogs/{id}", put(update_dog).delete(delete_dog))
-        .with_state(Arc::new(RwLock::new(db)))
+        if errors.is_empty() {
+            Ok((self.name.trim(), self.breed.trim()))
+        } else {
+            Err(errors)
+        }
+    }
 }
 
-async fn index() -> Response {
-    Html(include_str!("../../../templates/dogs.html")).into_response()
+pub struct Dogs {
+    state: SharedState,
+    changes: Changes,
+    store: SessionStore<SessionNullPool>,
+    purge: JoinHandle<()>,
+}
+
+impl Dogs {
+    pub async fn new(storage: Storage, seeds: &Seeds) -> anyhow::Result<Dogs> {
+        let db = DogDB::open(&storage, seeds)?;
+
+        let session_config = SessionConfig::default().with_table_name("dogs");
+        let store = SessionStore::<SessionNullPool>::new(None, session_config).await?;
+
+        let state = Arc::new(RwLock::new(db));
+        let purge = tokio::spawn(purge_tombstones(state.clone()));
+
+        Ok(Dogs {
+            state,
+            changes: Changes::new(),
+            store,
+            purge,
+        })
+    }
+}
+
+/// Runs forever, clearing out deleted dogs once they're past undoing.
+async fn purge_tombstones(state: SharedState) {
+    let mut interval = tokio::time::interval(UNDO_WINDOW);
+    loop {
+        interval.tick().await;
+        match state.write() {
+            Ok(mut db) => {
+                let purged = db.purge();
+                if purged > 0 {
+                    tracing::debug!("purged {} deleted dogs", purged);
+                }
+            }
+            Err(_) => tracing::error!("Write lock fail purging deleted dogs"),
+        }
+    }
+}
+
+impl App for Dogs {
+    fn name(&self) -> &'static str {
+        "Dogs"
+    }
+
+    fn slug(&self) -> &'static str {
+        "dogs"
+    }
+
+    fn description(&self) -> &'static str {
+        "Creating a CRUD application, updated to support editing"
+    }
+
+    fn chapter(&self) -> u8 {
+        3
+    }
+
+    fn routes(&self) -> Router {
+        // Fragments which can also be loaded as a full page
+        let form = Router::new()
+            .route("/dogs/form", get(stateful_form))
+            .route_layer(from_fn_with_state(FullPage(form_page), full_page_fallback));
+
+        let rows = Router::new()
+            .route("/dogs/{id}/row", get(show_row))
+            .route("/dogs/{id}/edit-row", get(edit_row))
+            .route_layer(from_fn_with_state(FullPage(rows_page), full_page_fallback));
+
+        let history = Router::new()
+            .route("/dogs/{id}/history", get(history::history))
+            .route_layer(from_fn_with_state(
+                FullPage(history::history_page),
+                full_page_fallback,
+            ));
+
+        let state = DogsState {
+            db: self.state.clone(),
+            changes: self.changes.clone(),
+        };
+
+        // No sessions needed for the API
+        let api = api::routes().with_state(state.clone());
+
+        Router::new()
+            .route("/dogs", get(index).post(add_dog))
+            .route("/dogs/table-rows", get(table_rows))
+            .route("/dogs/select/{id}", put(select_dog))
+            .route("/dogs/deselect", put(deselect_dog))
+            .route("/dogs/{id}", put(update_dog).delete(delete_dog))
+            .route("/dogs/{id}/restore", post(restore_dog))
+            .merge(history)
+            .route("/dogs/events", get(events::events))
+            .route("/dogs/export.csv", get(bulk::export_csv))
+            .route("/dogs/export.json", get(bulk::export_json))
+            .route("/dogs/import", get(bulk::import_page).post(bulk::commit))
+            .route("/dogs/import/preview", post(bulk::preview))
+            .merge(form)
+            .merge(rows)
+            .with_state(state)
+            .layer(SessionLayer::new(self.store.clone()))
+            .merge(api)
+    }
+
+    // Sessions use `SessionNullPool`, so they live in memory and there's nothing to save
+    fn shutdown(&self) -> anyhow::Result<()> {
+        self.purge.abort();
+        let db = self.state.read().map_err(|_| anyhow!("Read lock fail"))?;
+        db.flush()
+    }
+}
+
+async fn index(Query(listing): Query<Listing>) -> Markup {
+    page(None, None, &listing)
 }
 
 async fn add_dog(
+    hx: HxRequest,
+    headers: HeaderMap,
     State(state): State<SharedState>,
+    State(changes): State<Changes>,
+    session: DogSession,
     Form(form): Form<NewDog>,
 ) -> Result<Response, Mishap> {
-    let dog = Dog::new(&form.name, &form.breed);
+    let (name, breed) = match form.validate() {
+        Ok(valid) => valid,
+        Err(errors) => return Ok(invalid(blank_dog_form(&form, &errors))),
+    };
+
+    let dog = Dog::new(name, breed);
 
     let mut db = state
         .write()
-        .map_err(|_| Mishap(anyhow!("Write lock fail")))?;
+        .map_err(|_| Mishap::internal(anyhow!("Write lock fail")))?;
+
+    db.insert(dog.clone(), history::who(&session))?;
+    changes.publish(&headers, Change::Added(dog.clone()));
+
+    // Wherever the page's sort and search put it
+    let row = placed_row(&dog, &page_listing(&hx), &db, "#dogs-body")?;
 
-    db.insert(dog.clone());
-    let markup = dog_row(&dog, None);
-    Ok(markup.into_response())
+    // Reloading the form clears it, along with any earlier errors
+    let hx = HxResponse::new().trigger(SELECTION_CHANGE);
+    Ok((hx, row).into_response())
 }
 
 async fn delete_dog(
+    headers: HeaderMap,
     Path(id): Path<Uuid>,
     State(state): State<SharedState>,
+    State(changes): State<Changes>,
+    session: DogSession,
 ) -> Result<Response, Mishap> {
     let mut db = state
         .write()
-        .map_err(|_| Mishap(anyhow!("Write lock fail")))?;
+        .map_err(|_| Mishap::internal(anyhow!("Write lock fail")))?;
 
-    db.delete(id);
-    Ok(StatusCode::OK.into_response())
-}
+    let dog = db
+        .delete(id, history::who(&session))?
+        .ok_or_else(|| no_such_dog(&id))?;
+    deselect(&session);
+    changes.publish(&headers, Change::Deleted(dog.id.clone()));
 
-async fn stateful_form(State(state): State<SharedState>) -> Result<Response, Mishap> {
-    let db = state
-        .read()
-        .map_err(|_| Mishap(anyhow!("Read lock fail")))?;
-
-    if let Some(id) = db.selected_id {
-        let dog = db.find(&id).ok_or(Mishap(anyhow!("No such dog")))?;
-        Ok(dog_form(dog).into_response())
-    } else {
-        Ok(blank_dog_form().into_response())
+    // The row stays, hidden, so an undo can put the dog back in the same place
+    let row_id = format!("row-{}", dog.id);
+    Ok(html! {
+        tr id=(row_id) hidden {}
+        template { (undo_toast(&dog)) }
     }
+    .into_response())
 }
 
-async fn select_dog(
+async fn restore_dog(
+    hx: HxRequest,
+    headers: HeaderMap,
     Path(id): Path<Uuid>,
     State(state): State<SharedState>,
+    State(changes): State<Changes>,
+    session: DogSession,
 ) -> Result<Response, Mishap> {
     let mut db = state
         .write()
-        .map_err(|_| Mishap(anyhow!("Select write lock fail")))?;
+        .map_err(|_| Mishap::internal(anyhow!("Write lock fail")))?;
 
-    db.select(&id);
+    let dog = db
+        .restore(id, history::who(&session))?
+        .ok_or_else(|| Mishap::not_found("It's too late to undo that delete"))?;
+    changes.publish(&headers, Change::Added(dog.clone()));
 
-    Response::builder()
-        .header("HX-Trigger", "selection-change")
-        .status(200)
-        .body(Body::empty())
-        .map_err(|e| Mishap(anyhow!(e)))
+    let listing = page_listing(&hx);
+    Ok(dog_row(&dog, Some(true), &listing.q).into_response())
 }
 
-async fn deselect(State(state): State<SharedState>) -> Result<Response, Mishap> {
-    let mut db = state
-        .write()
-        .map_err(|_| Mishap(anyhow!("Select write lock fail")))?;
+async fn stateful_form(
+    State(state): State<SharedState>,
+    session: DogSession,
+) -> Result<Response, Mishap> {
+    let db = state
+        .read()
+        .map_err(|_| Mishap::internal(anyhow!("Read lock fail")))?;
+
+    // Someone else may have deleted the selected dog, which leaves nothing to edit
+    let selected = match selected_id(&session) {
+        Some(id) => db.find(&id)?,
+        None => None,
+    };
+
+    match selected {
+        Some(dog) => {
+            let input = NewDog::from_dog(&dog);
+            Ok(dog_form(&dog, &input, &Errors::default()).into_response())
+        }
+        None => {
+            deselect(&session);
+            Ok(blank_dog_form(&NewDog::default(), &Errors::default()).into_response())
+        }
+    }
+}
+
+async fn select_dog(
+    Path(id): Path<Uuid>,
+    State(state): State<SharedState>,
+    session: DogSession,
+) -> Result<HxResponse, Mishap> {
+    let db = state
+        .read()
+        .map_err(|_| Mishap::internal(anyhow!("Read lock fail")))?;
 
-    db.deselect();
+    db.find(&id)?.ok_or_else(|| no_such_dog(&id))?;
 
-    Response::builder()
-        .header("HX-Trigger", "selection-change")
-        .status(200)
-        .body(Body::empty())
-        .map_err(|e| Mishap(anyhow!(e)))
+    select(&session, &id);
+    Ok(HxResponse::new().trigger(SELECTION_CHANGE))
+}
+
+async fn deselect_dog(session: DogSession) -> HxResponse {
+    deselect(&session);
+    HxResponse::new().trigger(SELECTION_CHANGE)
 }
 
 async fn update_dog(
+    hx: HxRequest,
+    headers: HeaderMap,
     Path(id): Path<Uuid>,
     State(state): State<SharedState>,
+    State(changes): State<Changes>,
+    session: DogSession,
     Form(form): Form<NewDog>,
 ) -> Result<Response, Mishap> {
     let mut db = state
         .write()
-        .map_err(|_| Mishap(anyhow!("Write lock fail")))?;
+        .map_err(|_| Mishap::internal(anyhow!("Write lock fail")))?;
+
+    let mut dog = db.find(&id)?.ok_or_else(|| no_such_dog(&id))?;
+
+    // Saved from the row itself (see `dog_edit_row`) rather than the form above the table
+    let row_id = format!("row-{}", id);
+    let inline = hx.target.as_deref() == Some(row_id.as_str());
+
+    // Someone else saved a change after this form was loaded
+    if form.version.is_some_and(|version| version != dog.version) {
+        if inline {
+            // Saving again replaces their change, cancelling keeps it
+            let input = NewDog {
+                version: Some(dog.version),
+                ..form
+            };
+            let row = dog_edit_row(&dog, &input, &Errors::default(), Some(&dog));
+            return Ok((StatusCode::CONFLICT, row).into_response());
+        }
+        return Ok(conflict(&dog, &form));
+    }
+
+    let (name, breed) = match form.validate() {
+        Ok(valid) => valid,
+        Err(errors) if inline => {
+            let row = dog_edit_row(&dog, &form, &errors, None);
+            return Ok((StatusCode::UNPROCESSABLE_ENTITY, row).into_response());
+        }
+        Err(errors) => return Ok(invalid(dog_form(&dog, &form, &errors))),
+    };
 
-    let dog = db.find(&id).ok_or(Mishap(anyhow!("No such dog")))?;
+    dog.name = name.to_string();
+    dog.breed = breed.to_string();
+    dog.version += 1;
 
-    let mut dog = dog.clone();
-    dog.name = form.name;
-    dog.breed = form.breed;
+    db.insert(dog.clone(), history::who(&session))?;
+    changes.publish(&headers, Change::Updated(dog.clone()));
 
-    db.insert(dog.clone());
+    // Saved from the form or inline, the row moves to wherever the page's sort now puts it
+    let row = placed_row(&dog, &page_listing(&hx), &db, "#dogs-body")?;
+    let mut response = HxResponse::new().reswap("none");
 
-    let row_html: String = dog_row(&dog, Some(true)).into();
+    // The form is done with the dog, or would now show an old version of it
+    if !inline || selected_id(&session) == Some(id) {
+        deselect(&session);
+        response = response.trigger(SELECTION_CHANGE);
+    }
+    Ok((response, row).into_response())
+}
 
-    Response::builder()
-        .header("HX-Trigger", "selection-change")
-        .status(200)
-        .body(Body::from(row_html))
-        .map_err(|e| Mishap(anyhow!(e)))
+/// `GET /dogs/{id}/row`: the dog's row as usual, e.g. to cancel an inline edit
+async fn show_row(
+    hx: HxRequest,
+    Path(id): Path<Uuid>,
+    State(state): State<SharedState>,
+) -> Result<Markup, Mishap> {
+    let db = state
+        .read()
+        .map_err(|_| Mishap::internal(anyhow!("Read lock fail")))?;
+
+    let dog = db.find(&id)?.ok_or_else(|| no_such_dog(&id))?;
+    Ok(dog_row(&dog, None, &page_listing(&hx).q))
 }
 
-async fn table_rows(State(state): State<SharedState>) -> Result<Response, Mishap> {
+/// `GET /dogs/{id}/edit-row`: the dog's row with its name and breed ready to edit
+async fn edit_row(
+    Path(id): Path<Uuid>,
+    State(state): State<SharedState>,
+) -> Result<Markup, Mishap> {
+    let db = state
+        .read()
+        .map_err(|_| Mishap::internal(anyhow!("Read lock fail")))?;
+
+    let dog = db.find(&id)?.ok_or_else(|| no_such_dog(&id))?;
+    Ok(dog_edit_row(
+        &dog,
+        &NewDog::from_dog(&dog),
+        &Errors::default(),
+        None,
+    ))
+}
+
+/// `GET /dogs/table-rows`: a page of rows, for htmx.
+///
+/// Anything else is sent to the dogs page, which shows the same listing with
+/// headers and a search box to match (and loads these rows itself).
+async fn table_rows(
+    hx: HxRequest,
+    State(state): State<SharedState>,
+    Query(listing): Query<Listing>,
+    Query(cursor): Query<Cursor>,
+) -> Result<Response, Mishap> {
+    if !hx.request || hx.history_restore_request {
+        return Ok(Redirect::to(&listing.url("/dogs")).into_response());
+    }
+
     let dogdb = state
         .read()
-        .map_err(|_| Mishap(anyhow!("Read lock fail")))?;
+        .map_err(|_| Mishap::internal(anyhow!("Read lock fail")))?;
+
+    let after = cursor.position(&listing).map_err(Mishap::validation)?;
+    let (dogs, more) = dogdb.page(&listing, after.as_ref(), ROWS_PER_PAGE)?;
+
+    // The headers show the sort, so they change with it.
+    // (Not for later pages, which follow whatever the headers already say.)
+    let head = Some(true).filter(|_| after.is_none());
+
+    let markup = html! {
+        @for dog in &dogs {
+            (dog_row(dog, None, &listing.q))
+        }
+        @if let (true, Some(last)) = (more, dogs.last()) {
+            (more_row(&listing.next_url("/dogs/table-rows", &listing.after(last))))
+        }
+        @if head.is_some() {
+            // A bare `thead` after rows gets dropped by the HTML parser, but htmx
+            // looks for out-of-band swaps inside templates
+            template { (table_head(&listing, head)) }
+        }
+    };
+
+    if head.is_none() {
+        return Ok(markup.into_response());
+    }
+
+    // So a reload (or a shared link) shows the same dogs
+    let hx = HxResponse::new().replace_url(listing.url("/dogs"));
+    Ok((hx, markup).into_response())
+}
 
-    let frags: Vec<String> = dogdb
-        .dogs()
-        .iter()
-        .map(|dog| dog_row(dog, None))
-        .map(|m| m.into_string())
-        .collect();
+/// How the page making the request lists the dogs,
+/// so rows we send back keep highlighting whatever it's searching for.
+fn page_listing(hx: &HxRequest) -> Listing {
+    hx.current_url
+        .as_deref()
+        .and_then(Listing::from_url)
+        .unwrap_or_default()
+}
 
-    Ok(frags.concat().into_response())
+/// A form shown again with its errors, in place of the form that was submitted.
+/// (`mishap::render` leaves HTML error responses alone, so this is swapped in as-is.)
+fn invalid(form: Markup) -> Response {
+    let hx = HxResponse::new().retarget("#dog-form").reswap("outerHTML");
+    (StatusCode::UNPROCESSABLE_ENTITY, hx, form).into_response()
+}
+
+/// A 409 showing both versions of a dog, in place of the form, so the user can pick one.
+fn conflict(theirs: &Dog, mine: &NewDog) -> Response {
+    let hx = HxResponse::new().retarget("#dog-form").reswap("outerHTML");
+    (StatusCode::CONFLICT, hx, conflict_choice(theirs, mine)).into_response()
+}
+
+/// The 404 for an id that's well-formed, but not one of ours.
+/// (A malformed id is rejected as a 400 by `extract::Path`.)
+fn no_such_dog(id: &Uuid) -> Mishap {
+    Mishap::not_found(format!("There's no dog with id {}", id))
 }
 
 //
 // HTML serialization
 //
 
-fn blank_dog_form() -> Markup {
+/// The dogs page, optionally with the form or rows already filled in
+/// (for when a fragment is requested without htmx).
+/// Whichever part isn't supplied is loaded by htmx, as usual.
+fn page(form: Option<Markup>, rows: Option<Markup>, listing: &Listing) -> Markup {
+    let form_trigger = match form {
+        Some(_) => "selection-change from:body",
+        None => "revealed, selection-change from:body",
+    };
+
+    // Without rows, the table fetches them once it's on screen
+    let rows_trigger = Some("revealed").filter(|_| rows.is_none());
+    let rows_url = Some(listing.url("/dogs/table-rows")).filter(|_| rows.is_none());
+
+    // Identifies this page to the server, so it isn't sent its own changes
+    let client = Uuid::now_v7().to_string();
+    let hx_headers = serde_json::json!({ CLIENT_HEADER: client }).to_string();
+    let events_url = format!("/dogs/events?client={}", client);
+
+    let body = html! {
+        div hx-headers=(hx_headers) data-events=(events_url) {
+            h1 { "Dogs in Chapter 3" }
+
+            p class="bulk" {
+                a href="/dogs/export.csv" { "Export CSV" }
+                a href="/dogs/export.json" { "Export JSON" }
+                a href="/dogs/import" { "Import" }
+            }
+
+            div hx-trigger=(form_trigger) hx-get="/dogs/form" { (form.unwrap_or_default()) }
+
+            // Searches within the current sort, which is kept in the headers
+            input
+                type="search"
+                name="q"
+                value=(listing.q)
+                placeholder="Search names and breeds"
+                aria-label="Search names and breeds"
+                hx-get="/dogs/table-rows"
+                hx-trigger="keyup changed delay:300ms, search"
+                hx-target="#dogs-body"
+                hx-include="#dogs-head" {}
+
+            table hx-trigger=[rows_trigger] hx-get=[rows_url] hx-target="#dogs-body" {
+                (table_head(listing, None))
+                tbody id="dogs-body" { (rows.unwrap_or_default()) }
+            }
+
+            div id="dog-toasts" aria-live="polite" {}
+        }
+
+        // Live updates from other pages
+        script src="/assets/dogs.js" {}
+    };
+
+    layout("Dogs CRUD", &["/assets/dogs.css"], Some(home_nav()), body)
+}
+
+fn form_page(form: Markup) -> Markup {
+    page(Some(form), None, &Listing::default())
+}
+
+fn rows_page(rows: Markup) -> Markup {
+    page(None, Some(rows), &Listing::default())
+}
+
+/// Column headers which sort the table when clicked.
+///
+/// They also hold the current sort, for the search input to include.
+fn table_head(listing: &Listing, swap_oob: Option<bool>) -> Markup {
+    html! {
+        thead id="dogs-head" hx-swap-oob=[swap_oob] {
+            tr {
+                (sort_header("Name", SortKey::Name, listing))
+                (sort_header("Breed", SortKey::Breed, listing))
+                // Over the buttons, which have no heading of their own
+                th {
+                    input type="hidden" name="sort" value=(listing.sort.as_str()) {}
+                    input type="hidden" name="dir" value=(listing.dir.as_str()) {}
+                }
+                th { "History" }
+            }
+        }
+    }
+}
+
+fn sort_header(label: &str, key: SortKey, listing: &Listing) -> Markup {
+    let next = listing.toggle(key).url("/dogs/table-rows");
+    let aria_sort = Some(listing.dir.aria()).filter(|_| listing.sort == key);
+
+    html! {
+        th aria-sort=[aria_sort] {
+            button class="sort" hx-get=(next) type="button" { (label) }
+        }
+    }
+}
+
+fn blank_dog_form(input: &NewDog, errors: &Errors) -> Markup {
     html! {
         form
+            id="dog-form"
             hx-disabled-elt="#submit-btn"
             hx-post="/dogs"
-            hx-target="tbody"
-            hx-swap="afterbegin" {
-                div {
-                    label for="name" { "Name" }
-                    input id="name" name="name" required size="30" type="text" {}
-                }
-                div {
-                    label for="breed" { "Breed" }
-                    input  id="breed" name="breed" required size="30" type="text" {}
-                }
+            hx-swap="none" {
+                (dog_fields(input, errors))
                 div class="buttons" {
                     button id="submit-btn" { "Add" }
                 }
@@ -253,57 +891,274 @@ fn blank_dog_form() -> Markup {
     }
 }
 
-fn dog_form(dog: &Dog) -> Markup {
+fn dog_form(dog: &Dog, input: &NewDog, errors: &Errors) -> Markup {
     let dog_url = format!("/dogs/{}", dog.id);
 
     html! {
         form
+            id="dog-form"
             hx-disabled-elt="#submit-btn"
             hx-put=(dog_url) {
-                div {
-                    label for="name" { "Name" }
-                    input id="name" name="name" required size="30" type="text" value=(dog.name) {}
-                }
-                div {
-                    label for="breed" { "Breed" }
-                    input  id="breed" name="breed" required size="30" type="text" value=(dog.breed) {}
-                }
+                (dog_fields(input, errors))
+                input type="hidden" name="version" value=[input.version] {}
                 div class="buttons" {
                     button id="submit-btn" { "Update" }
-                    button hx-put="/dogs/deselect" hx-swap="none" type="buttin" { "Cancel" }
+                    button hx-put="/dogs/deselect" hx-swap="none" type="button" { "Cancel" }
+                }
+
+            }
+    }
+}
+
+/// Replaces the form when an update was based on an out of date copy of the dog.
+///
+/// "Keep theirs" reloads the form with what's saved now.
+/// "Use mine" submits the user's values again, this time against the current version.
+fn conflict_choice(theirs: &Dog, mine: &NewDog) -> Markup {
+    let dog_url = format!("/dogs/{}", theirs.id);
+
+    html! {
+        div id="dog-form" class="conflict" {
+            p { strong { "Someone else changed this dog while you were editing it." } }
+            dl {
+                dt { "Saved" }
+                dd { (theirs.name) ", " (theirs.breed) }
+                dt { "Yours" }
+                dd { (mine.name) ", " (mine.breed) }
+            }
+            div class="buttons" {
+                button hx-get="/dogs/form" hx-target="#dog-form" hx-swap="outerHTML" type="button" {
+                    "Keep theirs"
+                }
+                form hx-put=(dog_url) {
+                    input type="hidden" name="name" value=(mine.name) {}
+                    input type="hidden" name="breed" value=(mine.breed) {}
+                    input type="hidden" name="version" value=(theirs.version) {}
+                    button { "Use mine" }
                 }
+            }
+        }
+    }
+}
 
+/// The name and breed inputs, keeping whatever was typed, with any problems alongside.
+fn dog_fields(input: &NewDog, errors: &Errors) -> Markup {
+    html! {
+        (dog_field("name", "Name", &input.name, errors.field("name")))
+        (dog_field("breed", "Breed", &input.breed, errors.field("breed")))
+    }
+}
+
+fn dog_field(name: &str, label: &str, value: &str, errors: &[String]) -> Markup {
+    let invalid = Some("true").filter(|_| !errors.is_empty());
+
+    html! {
+        div {
+            label for=(name) { (label) }
+            input id=(name) name=(name) required size="30" type="text" value=(value) aria-invalid=[invalid] {}
+            @for error in errors {
+                span class="error" { (error) }
             }
+        }
     }
 }
 
-fn dog_row(dog: &Dog, swap_oob: Option<bool>) -> Markup {
+/// A row of the table, with anything matching the search `q` highlighted.
+fn dog_row(dog: &Dog, swap_oob: Option<bool>, q: &str) -> Markup {
     let dog_url = format!("/dogs/{}", dog.id);
     let dog_edit_url = format!("/dogs/select/{}", dog.id);
+    let edit_row_url = format!("/dogs/{}/edit-row", dog.id);
+    let history_url = format!("/dogs/{}/history", dog.id);
 
     // Our IDs are UUIDs, which can start with a number, but that would not be a valid CSS selector
     let row_id = format!("row-{}", dog.id);
 
     html! {
         tr class="on-hover" id=(row_id)  hx-swap-oob=[swap_oob] {
-            td { (dog.name)  }
-            td { (dog.breed) }
+            td { (highlight(&dog.name, q)) }
+            td { (highlight(&dog.breed, q)) }
             td class="buttons" {
                 button
                     class="show-on-hover"
                     hx-delete=(dog_url)
-                    hx-confirm="Are you sure?"
                     hx-target="closest tr"
                     hx-swap="outerHTML"
                     type="button"
                     { "x" }
+                button
+                    class="show-on-hover"
+                    hx-get=(edit_row_url)
+                    hx-target="closest tr"
+                    hx-swap="outerHTML"
+                    type="button"
+                    { "Edit" }
                 button
                     class="show-on-hover"
                     hx-put=(dog_edit_url)
                     hx-swap="none"
                     type="button"
-                    { "Edit" }
+                    { "Edit above" }
+            }
+            td {
+                // Loaded each time it's opened, so it's never out of date
+                details
+                    class="history"
+                    hx-get=(history_url)
+                    hx-trigger="toggle[this.open]"
+                    hx-target="find .history-panel" {
+                        summary { "History" }
+                        div class="history-panel" {}
+                    }
+            }
+        }
+    }
+}
+
+/// Out-of-band swaps moving a dog's row to where it belongs in a table listing the dogs
+/// with `listing`, whose `tbody` is selected by `body`.
+///
+/// The row is taken out, and put back (if it matches the search) in front of the dog that
+/// follows it. Swaps with no target do nothing, so it's only put back if that row has been
+/// loaded, or the loaded rows stop just where it goes; otherwise a later page brings it.
+fn placed_row(dog: &Dog, listing: &Listing, db: &DogDB, body: &str) -> anyhow::Result<Markup> {
+    let remove = format!("delete:{} > #row-{}", body, dog.id);
+    if !listing.matches(dog) {
+        return Ok(html! { template { tr hx-swap-oob=(remove) {} } });
+    }
+
+    let after = listing.after(dog);
+    let (mut next, _) = db.page(listing, Some(&after), 1)?;
+    let insert = match next.pop() {
+        Some(next) => format!("beforebegin:{} > #row-{}", body, next.id),
+        // The last dog, which is only shown once there's no more to load
+        None => format!("beforeend:{}:not(:has(> tr.more))", body),
+    };
+    // Rows that stopped at this dog (before it was changed, or deleted) won't load it again.
+    // Their `more_row` is found by its URL, which has nothing a CSS string needs escaped.
+    let stopped = format!(
+        "beforebegin:{} > tr.more[hx-get=\"{}\"]",
+        body,
+        listing.next_url("/dogs/table-rows", &after)
+    );
+
+    // Templates, so the HTML parser keeps each part where it is
+    let row = dog_row(dog, None, &listing.q);
+    Ok(html! {
+        template { tr hx-swap-oob=(remove) {} }
+        @for insert in [insert, stopped] {
+            template {
+                tbody hx-swap-oob=(insert) { (row) }
+            }
+        }
+    })
+}
+
+/// A dog's row, edited in place: Enter saves and Escape cancels.
+///
+/// `theirs` is the saved dog, when a save was based on an older version.
+/// The input's version is then the saved one, so saving again overwrites it.
+fn dog_edit_row(dog: &Dog, input: &NewDog, errors: &Errors, theirs: Option<&Dog>) -> Markup {
+    let dog_url = format!("/dogs/{}", dog.id);
+    let row_url = format!("/dogs/{}/row", dog.id);
+    let row_id = format!("row-{}", dog.id);
+
+    html! {
+        tr class="editing" id=(row_id) {
+            td { (inline_field("name", "Name", &input.name, errors.field("name"), true)) }
+            td { (inline_field("breed", "Breed", &input.breed, errors.field("breed"), false)) }
+            td class="buttons" {
+                input type="hidden" name="version" value=[input.version] {}
+                // Enter in an input, not on a button (which clicks it anyway)
+                button
+                    hx-put=(dog_url)
+                    hx-include="closest tr"
+                    hx-target="closest tr"
+                    hx-swap="outerHTML"
+                    hx-trigger="click, keyup[key=='Enter'&&target.tagName=='INPUT'] from:closest tr"
+                    type="button"
+                    { "Save" }
+                button
+                    hx-get=(row_url)
+                    hx-target="closest tr"
+                    hx-swap="outerHTML"
+                    hx-trigger="click, keyup[key=='Escape'] from:closest tr"
+                    type="button"
+                    { "Cancel" }
+            }
+            td {
+                @if let Some(theirs) = theirs {
+                    span class="error" {
+                        "Someone else changed this to " (theirs.name) ", " (theirs.breed) ". "
+                        "Save to replace that, or cancel to keep it."
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// An input for `dog_edit_row`, labelled for screen readers only (the column header says what it is)
+fn inline_field(name: &str, label: &str, value: &str, errors: &[String], focus: bool) -> Markup {
+    let invalid = Some("true").filter(|_| !errors.is_empty());
+
+    html! {
+        input name=(name) aria-label=(label) required type="text" value=(value) aria-invalid=[invalid] autofocus[focus] {}
+        @for error in errors {
+            span class="error" { (error) }
+        }
+    }
+}
+
+/// The last row of a page, which replaces itself with the next page once it's scrolled
+/// into view (or clicked, should that not happen).
+///
+/// The table targets `#dogs-body`, so this targets itself instead.
+fn more_row(next_url: &str) -> Markup {
+    html! {
+        tr class="more" hx-get=(next_url) hx-trigger="revealed, click" hx-target="this" hx-swap="outerHTML" {
+            td colspan="4" {
+                button type="button" { "Load more" }
+            }
+        }
+    }
+}
+
+/// Offers to undo a delete, until it fades away at the end of `UNDO_WINDOW`.
+///
+/// Undoing puts the row back (out-of-band) and removes the toast.
+fn undo_toast(dog: &Dog) -> Markup {
+    let restore_url = format!("/dogs/{}/restore", dog.id);
+    let fade = format!("animation-delay: {}s", UNDO_WINDOW.as_secs());
+
+    html! {
+        div hx-swap-oob="beforeend:#dog-toasts" {
+            div class="toast undo" style=(fade) hx-on:animationend="this.remove()" {
+                "Deleted " (dog.name) " — "
+                button
+                    hx-post=(restore_url)
+                    hx-target="closest .toast"
+                    hx-swap="delete"
+                    type="button"
+                    { "Undo" }
             }
         }
     }
 }
+
+/// `text` with each match for `q` in a `mark`.
+fn highlight(text: &str, q: &str) -> Markup {
+    let mut parts = Vec::new();
+    let mut last = 0;
+    for range in find(text, q) {
+        parts.push((&text[last..range.start], false));
+        parts.push((&text[range.clone()], true));
+        last = range.end;
+    }
+    parts.push((&text[last..], false));
+
+    html! {
+        @for (part, found) in parts {
+            @if found { mark { (part) } } @else { (part) }
+        }
+    }
+}
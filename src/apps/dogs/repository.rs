@@ -0,0 +1,115 @@
+use super::{
+    history::Entry,
+    listing::{After, Listing},
+    Dog,
+};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Where dogs are kept.
+///
+/// Handlers only ever talk to a repository through `DogDB`,
+/// so swapping the in-memory map for SQLite (or anything else)
+/// doesn't change the routes.
+pub trait DogRepository: Send + Sync {
+    /// Every dog, in no particular order.
+    fn all(&self) -> anyhow::Result<Vec<Dog>>;
+
+    fn find(&self, id: &Uuid) -> anyhow::Result<Option<Dog>>;
+
+    /// Up to `limit` dogs matching the listing's search, in its order,
+    /// starting after `after` (or from the start).
+    fn page(
+        &self,
+        listing: &Listing,
+        after: Option<&After>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<Dog>>;
+
+    // Each change is saved along with its history entries, or not at all,
+    // so the history never misses a change (or records one that didn't happen).
+
+    /// Insert a new dog, or replace the dog with the same id.
+    fn save(&mut self, dog: Dog, entry: Entry) -> anyhow::Result<()>;
+
+    /// Insert or replace several dogs: all of them, or (on error) none of them.
+    fn save_all(&mut self, dogs: Vec<Dog>, entries: Vec<Entry>) -> anyhow::Result<()>;
+
+    /// Remove a dog, giving back what was removed (if anything).
+    /// The entry is only recorded if there was a dog to remove.
+    fn delete(&mut self, id: &Uuid, entry: Entry) -> anyhow::Result<Option<Dog>>;
+
+    /// Changes to one dog, oldest first.
+    fn history(&self, id: &Uuid) -> anyhow::Result<Vec<Entry>>;
+
+    /// Make sure everything written so far is stored, ready to exit.
+    fn flush(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// The original fake database: gone on restart.
+#[derive(Default)]
+pub struct MemoryRepository {
+    dogs: HashMap<String, Dog>,
+    history: Vec<Entry>,
+}
+
+impl DogRepository for MemoryRepository {
+    fn all(&self) -> anyhow::Result<Vec<Dog>> {
+        Ok(self.dogs.values().cloned().collect())
+    }
+
+    fn find(&self, id: &Uuid) -> anyhow::Result<Option<Dog>> {
+        Ok(self.dogs.get(&id.to_string()).cloned())
+    }
+
+    fn page(
+        &self,
+        listing: &Listing,
+        after: Option<&After>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<Dog>> {
+        let mut dogs: Vec<Dog> = self
+            .dogs
+            .values()
+            .filter(|dog| listing.matches(dog))
+            .filter(|dog| after.is_none_or(|after| listing.is_after(dog, after)))
+            .cloned()
+            .collect();
+        listing.sort(&mut dogs);
+        dogs.truncate(limit);
+        Ok(dogs)
+    }
+
+    fn save(&mut self, dog: Dog, entry: Entry) -> anyhow::Result<()> {
+        self.dogs.insert(dog.id.clone(), dog);
+        self.history.push(entry);
+        Ok(())
+    }
+
+    fn save_all(&mut self, dogs: Vec<Dog>, entries: Vec<Entry>) -> anyhow::Result<()> {
+        self.dogs
+            .extend(dogs.into_iter().map(|dog| (dog.id.clone(), dog)));
+        self.history.extend(entries);
+        Ok(())
+    }
+
+    fn delete(&mut self, id: &Uuid, entry: Entry) -> anyhow::Result<Option<Dog>> {
+        let deleted = self.dogs.remove(&id.to_string());
+        if deleted.is_some() {
+            self.history.push(entry);
+        }
+        Ok(deleted)
+    }
+
+    fn history(&self, id: &Uuid) -> anyhow::Result<Vec<Entry>> {
+        let id = id.to_string();
+        Ok(self
+            .history
+            .iter()
+            .filter(|entry| entry.dog_id == id)
+            .cloned()
+            .collect())
+    }
+}
@@ -0,0 +1,325 @@
+use super::{
+    history::Entry,
+    listing::{folded, folded_search, sort_key, After, Direction, Listing, SortKey},
+    repository::DogRepository,
+    Dog,
+};
+use anyhow::{anyhow, Context};
+use rusqlite::{params, Connection, OptionalExtension, Row, ToSql, Transaction};
+use std::{
+    path::Path,
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use uuid::Uuid;
+
+/// Schema changes, applied in order.
+///
+/// The index of the last migration applied is kept in SQLite's `user_version`,
+/// so only add to the end of this list: never edit an entry that has shipped.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE dogs (
+        id    TEXT PRIMARY KEY NOT NULL,
+        name  TEXT NOT NULL,
+        breed TEXT NOT NULL
+    );",
+    "ALTER TABLE dogs ADD COLUMN version INTEGER NOT NULL DEFAULT 1;",
+    // `at` is milliseconds since the Unix epoch; `before` and `after` are JSON
+    "CREATE TABLE dog_history (
+        seq     INTEGER PRIMARY KEY AUTOINCREMENT,
+        dog_id  TEXT NOT NULL,
+        at      INTEGER NOT NULL,
+        session TEXT,
+        before  TEXT,
+        after   TEXT
+    );
+    CREATE INDEX dog_history_dog_id ON dog_history (dog_id);",
+    // What the table is sorted and searched by, worked out in Rust (see `upsert`)
+    // so it matches `Listing` exactly, and indexed for paging
+    "ALTER TABLE dogs ADD COLUMN name_key TEXT NOT NULL DEFAULT '';
+    ALTER TABLE dogs ADD COLUMN breed_key TEXT NOT NULL DEFAULT '';
+    ALTER TABLE dogs ADD COLUMN name_search TEXT NOT NULL DEFAULT '';
+    ALTER TABLE dogs ADD COLUMN breed_search TEXT NOT NULL DEFAULT '';
+    CREATE INDEX dogs_name_key ON dogs (name_key, id);
+    CREATE INDEX dogs_breed_key ON dogs (breed_key, id);",
+];
+
+/// The number of migrations before the sort and search columns were added,
+/// which are filled in for older dogs by `fill_keys`
+const BEFORE_KEYS: usize = 3;
+
+/// Insert, or replace, a dog
+const UPSERT: &str =
+    "INSERT INTO dogs (id, name, breed, version, name_key, breed_key, name_search, breed_search)
+     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+     ON CONFLICT (id) DO UPDATE
+     SET name = excluded.name, breed = excluded.breed, version = excluded.version,
+         name_key = excluded.name_key, breed_key = excluded.breed_key,
+         name_search = excluded.name_search, breed_search = excluded.breed_search";
+
+/// Dogs stored in a local SQLite file.
+pub struct SqliteRepository {
+    // `Connection` is `Send` but not `Sync`, and the repository is shared between handlers
+    conn: Mutex<Connection>,
+
+    /// True if the database was created (rather than re-opened) by `open`
+    pub fresh: bool,
+}
+
+impl SqliteRepository {
+    pub fn open(path: &Path) -> anyhow::Result<SqliteRepository> {
+        let mut conn = Connection::open(path)
+            .with_context(|| format!("Unable to open dogs database {}", path.display()))?;
+
+        let applied = migrate(&mut conn)?;
+        if applied <= BEFORE_KEYS {
+            fill_keys(&mut conn)?;
+        }
+        tracing::info!(
+            "dogs database {} at version {}",
+            path.display(),
+            MIGRATIONS.len()
+        );
+
+        Ok(SqliteRepository {
+            conn: Mutex::new(conn),
+            fresh: applied == 0,
+        })
+    }
+
+    fn conn(&self) -> anyhow::Result<std::sync::MutexGuard<'_, Connection>> {
+        self.conn.lock().map_err(|_| anyhow!("SQLite lock fail"))
+    }
+}
+
+/// Brings the schema up to date, returning the version we started from.
+fn migrate(conn: &mut Connection) -> anyhow::Result<usize> {
+    let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+
+    if version > MIGRATIONS.len() {
+        return Err(anyhow!(
+            "Dogs database is at version {} but this build only knows {}",
+            version,
+            MIGRATIONS.len()
+        ));
+    }
+
+    let tx = conn.transaction()?;
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        tx.execute_batch(migration)
+            .with_context(|| format!("Migration {} failed", index + 1))?;
+    }
+    tx.pragma_update(None, "user_version", MIGRATIONS.len())?;
+    tx.commit()?;
+
+    Ok(version)
+}
+
+/// Fill in the sort and search columns for dogs saved before they existed
+fn fill_keys(conn: &mut Connection) -> anyhow::Result<()> {
+    let tx = conn.transaction()?;
+    {
+        let dogs: Vec<Dog> = tx
+            .prepare("SELECT id, name, breed, version FROM dogs")?
+            .query_map([], to_dog)?
+            .collect::<Result<_, _>>()?;
+
+        let mut stmt = tx.prepare(UPSERT)?;
+        for dog in &dogs {
+            upsert(&mut stmt, dog)?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+fn upsert(stmt: &mut rusqlite::Statement, dog: &Dog) -> rusqlite::Result<usize> {
+    stmt.execute(params![
+        dog.id,
+        dog.name,
+        dog.breed,
+        dog.version,
+        sort_key(&dog.name),
+        sort_key(&dog.breed),
+        folded(&dog.name),
+        folded(&dog.breed),
+    ])
+}
+
+fn to_dog(row: &Row) -> rusqlite::Result<Dog> {
+    Ok(Dog {
+        id: row.get("id")?,
+        name: row.get("name")?,
+        breed: row.get("breed")?,
+        version: row.get("version")?,
+    })
+}
+
+fn to_entry(row: &Row) -> rusqlite::Result<Entry> {
+    let millis: u64 = row.get("at")?;
+    Ok(Entry {
+        dog_id: row.get("dog_id")?,
+        at: UNIX_EPOCH + Duration::from_millis(millis),
+        session: row.get("session")?,
+        before: from_json(row, "before")?,
+        after: from_json(row, "after")?,
+    })
+}
+
+fn from_json(row: &Row, column: &str) -> rusqlite::Result<Option<Dog>> {
+    let json: Option<String> = row.get(column)?;
+    json.map(|json| serde_json::from_str(&json))
+        .transpose()
+        .map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, e.into())
+        })
+}
+
+/// Add to the history, as part of the transaction making the change
+fn record(tx: &Transaction, entries: Vec<Entry>) -> anyhow::Result<()> {
+    let mut stmt = tx.prepare_cached(
+        "INSERT INTO dog_history (dog_id, at, session, before, after)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?;
+    for entry in entries {
+        let before = entry
+            .before
+            .map(|dog| serde_json::to_string(&dog))
+            .transpose()?;
+        let after = entry
+            .after
+            .map(|dog| serde_json::to_string(&dog))
+            .transpose()?;
+        stmt.execute(params![
+            entry.dog_id,
+            millis(entry.at),
+            entry.session,
+            before,
+            after
+        ])?;
+    }
+    Ok(())
+}
+
+fn millis(at: SystemTime) -> u64 {
+    at.duration_since(UNIX_EPOCH)
+        .map(|since| since.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+impl DogRepository for SqliteRepository {
+    fn all(&self) -> anyhow::Result<Vec<Dog>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare_cached("SELECT id, name, breed, version FROM dogs")?;
+        let dogs = stmt.query_map([], to_dog)?.collect::<Result<_, _>>()?;
+        Ok(dogs)
+    }
+
+    fn find(&self, id: &Uuid) -> anyhow::Result<Option<Dog>> {
+        let conn = self.conn()?;
+        let mut stmt =
+            conn.prepare_cached("SELECT id, name, breed, version FROM dogs WHERE id = ?1")?;
+        let dog = stmt.query_row([id.to_string()], to_dog).optional()?;
+        Ok(dog)
+    }
+
+    fn save(&mut self, dog: Dog, entry: Entry) -> anyhow::Result<()> {
+        self.save_all(vec![dog], vec![entry])
+    }
+
+    /// Walks the index for the sort from `after`, so a page doesn't read the whole table.
+    /// (A search still has to look at every row it passes, until the page is full.)
+    fn page(
+        &self,
+        listing: &Listing,
+        after: Option<&After>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<Dog>> {
+        let key = match listing.sort {
+            SortKey::Created => None,
+            SortKey::Name => Some("name_key"),
+            SortKey::Breed => Some("breed_key"),
+        };
+        let (beyond, order) = match listing.dir {
+            Direction::Asc => (">", "ASC"),
+            Direction::Desc => ("<", "DESC"),
+        };
+
+        let q = folded_search(&listing.q);
+        let limit = i64::try_from(limit).unwrap_or(i64::MAX);
+        let mut params: Vec<(&str, &dyn ToSql)> = vec![(":limit", &limit)];
+
+        let mut sql = String::from("SELECT id, name, breed, version FROM dogs WHERE true");
+        if let Some(q) = &q {
+            sql.push_str(" AND (instr(name_search, :q) > 0 OR instr(breed_search, :q) > 0)");
+            params.push((":q", q));
+        }
+        if let Some(after) = after {
+            match key {
+                Some(key) => {
+                    sql.push_str(&format!(" AND ({}, id) {} (:key, :id)", key, beyond));
+                    params.push((":key", &after.key));
+                }
+                None => sql.push_str(&format!(" AND id {} :id", beyond)),
+            }
+            params.push((":id", &after.id));
+        }
+        match key {
+            Some(key) => sql.push_str(&format!(" ORDER BY {} {}, id {}", key, order, order)),
+            None => sql.push_str(&format!(" ORDER BY id {}", order)),
+        }
+        sql.push_str(" LIMIT :limit");
+
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare_cached(&sql)?;
+        let dogs = stmt
+            .query_map(params.as_slice(), to_dog)?
+            .collect::<Result<_, _>>()?;
+        Ok(dogs)
+    }
+
+    fn save_all(&mut self, dogs: Vec<Dog>, entries: Vec<Entry>) -> anyhow::Result<()> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(UPSERT)?;
+            for dog in &dogs {
+                upsert(&mut stmt, dog)?;
+            }
+        }
+        record(&tx, entries)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn delete(&mut self, id: &Uuid, entry: Entry) -> anyhow::Result<Option<Dog>> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        let dog = tx
+            .prepare_cached("DELETE FROM dogs WHERE id = ?1 RETURNING id, name, breed, version")?
+            .query_row([id.to_string()], to_dog)
+            .optional()?;
+        if dog.is_some() {
+            record(&tx, vec![entry])?;
+        }
+        tx.commit()?;
+        Ok(dog)
+    }
+
+    fn history(&self, id: &Uuid) -> anyhow::Result<Vec<Entry>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT dog_id, at, session, before, after FROM dog_history
+             WHERE dog_id = ?1 ORDER BY seq",
+        )?;
+        let entries = stmt
+            .query_map([id.to_string()], to_entry)?
+            .collect::<Result<_, _>>()?;
+        Ok(entries)
+    }
+
+    fn flush(&self) -> anyhow::Result<()> {
+        self.conn()?.cache_flush()?;
+        Ok(())
+    }
+}
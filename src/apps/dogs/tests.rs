@@ -0,0 +1,269 @@
+use super::{
+    listing::{After, Direction, Listing, SortKey},
+    Dog, DogDB, Seeds, Storage,
+};
+use std::path::PathBuf;
+use uuid::Uuid;
+
+/// Paging should work the same whichever repository is underneath
+fn databases() -> Vec<DogDB> {
+    let storages = [Storage::Memory, Storage::Sqlite(PathBuf::from(":memory:"))];
+    storages
+        .iter()
+        .map(|storage| DogDB::open(storage, &Seeds(Vec::new())).unwrap())
+        .collect()
+}
+
+/// Dogs created in the order given, so creation order isn't name order
+fn add_dogs(db: &mut DogDB, names: &[&str]) {
+    for (index, name) in names.iter().enumerate() {
+        let dog = Dog {
+            id: Uuid::from_u128(index as u128 + 1).to_string(),
+            ..Dog::new(name, "Mongrel")
+        };
+        db.insert(dog, None).unwrap();
+    }
+}
+
+const NAMES: &[&str] = &["Gus", "ada", "Fox", "Bo", "Eve", "cy", "Dot"];
+
+fn listing(sort: SortKey, dir: Direction) -> Listing {
+    Listing {
+        sort,
+        dir,
+        ..Listing::default()
+    }
+}
+
+/// Every page of the listing, as names, with whether each said there was more
+fn pages(db: &DogDB, listing: &Listing, limit: usize) -> Vec<(Vec<String>, bool)> {
+    let mut pages = Vec::new();
+    let mut after = None;
+    loop {
+        let (dogs, more) = db.page(listing, after.as_ref(), limit).unwrap();
+        after = dogs.last().map(|dog| listing.after(dog));
+        pages.push((dogs.into_iter().map(|dog| dog.name).collect(), more));
+        if !more {
+            return pages;
+        }
+    }
+}
+
+fn page_of(names: &[&str], more: bool) -> (Vec<String>, bool) {
+    (names.iter().map(|name| name.to_string()).collect(), more)
+}
+
+#[test]
+fn pages_in_creation_order() {
+    for mut db in databases() {
+        add_dogs(&mut db, NAMES);
+
+        let asc = listing(SortKey::Created, Direction::Asc);
+        assert_eq!(
+            pages(&db, &asc, 3),
+            vec![
+                page_of(&["Gus", "ada", "Fox"], true),
+                page_of(&["Bo", "Eve", "cy"], true),
+                page_of(&["Dot"], false),
+            ]
+        );
+
+        let desc = listing(SortKey::Created, Direction::Desc);
+        assert_eq!(
+            pages(&db, &desc, 3),
+            vec![
+                page_of(&["Dot", "cy", "Eve"], true),
+                page_of(&["Bo", "Fox", "ada"], true),
+                page_of(&["Gus"], false),
+            ]
+        );
+    }
+}
+
+#[test]
+fn pages_by_name_ignoring_case() {
+    for mut db in databases() {
+        add_dogs(&mut db, NAMES);
+
+        let asc = listing(SortKey::Name, Direction::Asc);
+        assert_eq!(
+            pages(&db, &asc, 3),
+            vec![
+                page_of(&["ada", "Bo", "cy"], true),
+                page_of(&["Dot", "Eve", "Fox"], true),
+                page_of(&["Gus"], false),
+            ]
+        );
+
+        let desc = listing(SortKey::Name, Direction::Desc);
+        assert_eq!(
+            pages(&db, &desc, 3),
+            vec![
+                page_of(&["Gus", "Fox", "Eve"], true),
+                page_of(&["Dot", "cy", "Bo"], true),
+                page_of(&["ada"], false),
+            ]
+        );
+    }
+}
+
+#[test]
+fn last_page_when_the_dogs_fill_the_pages_exactly() {
+    for mut db in databases() {
+        add_dogs(&mut db, &NAMES[..6]);
+
+        let asc = listing(SortKey::Name, Direction::Asc);
+        assert_eq!(
+            pages(&db, &asc, 3),
+            vec![
+                page_of(&["ada", "Bo", "cy"], true),
+                page_of(&["Eve", "Fox", "Gus"], false),
+            ]
+        );
+    }
+}
+
+#[test]
+fn pages_a_search() {
+    for mut db in databases() {
+        add_dogs(&mut db, &["Ève", "Evan", "Bo", "Steve", "Neve"]);
+
+        let search = Listing {
+            q: "EVE".to_string(),
+            ..listing(SortKey::Name, Direction::Asc)
+        };
+        // (Sorting ignores case, but not accents)
+        assert_eq!(
+            pages(&db, &search, 2),
+            vec![page_of(&["Neve", "Steve"], true), page_of(&["Ève"], false)]
+        );
+    }
+}
+
+#[test]
+fn carries_on_after_the_last_dog_shown_is_renamed() {
+    for mut db in databases() {
+        add_dogs(&mut db, NAMES);
+        let asc = listing(SortKey::Name, Direction::Asc);
+
+        let (first, _) = db.page(&asc, None, 3).unwrap();
+        let after = asc.after(first.last().unwrap());
+
+        let mut renamed = first.last().unwrap().clone();
+        renamed.name = "Zed".to_string();
+        db.insert(renamed, None).unwrap();
+
+        let (next, more) = db.page(&asc, Some(&after), 3).unwrap();
+        let names: Vec<&str> = next.iter().map(|dog| dog.name.as_str()).collect();
+        assert_eq!(names, ["Dot", "Eve", "Fox"]);
+        assert!(more);
+    }
+}
+
+#[test]
+fn carries_on_after_the_last_dog_shown_is_deleted() {
+    for mut db in databases() {
+        add_dogs(&mut db, NAMES);
+
+        for dir in [Direction::Asc, Direction::Desc] {
+            let listing = listing(SortKey::Name, dir);
+            let (first, _) = db.page(&listing, None, 2).unwrap();
+            let last = first.last().unwrap();
+            let after = listing.after(last);
+
+            let (expected, _) = db.page(&listing, Some(&after), 2).unwrap();
+            let id = Uuid::parse_str(&last.id).unwrap();
+            db.delete(id, None).unwrap();
+
+            let (next, _) = db.page(&listing, Some(&after), 2).unwrap();
+            let names = |dogs: &[Dog]| dogs.iter().map(|dog| dog.name.clone()).collect::<Vec<_>>();
+            assert_eq!(names(&next), names(&expected));
+
+            db.restore(id, None).unwrap();
+        }
+    }
+}
+
+/// A page's table, as paging and `placed_row` change it:
+/// the dogs shown, and where the next page starts if there's more
+struct Table {
+    ids: Vec<String>,
+    more: Option<After>,
+}
+
+impl Table {
+    fn load(db: &DogDB, listing: &Listing, limit: usize) -> Table {
+        let mut table = Table {
+            ids: Vec::new(),
+            more: None,
+        };
+        table.load_more(db, listing, limit, None);
+        table
+    }
+
+    fn load_more(&mut self, db: &DogDB, listing: &Listing, limit: usize, after: Option<After>) {
+        let (dogs, more) = db.page(listing, after.as_ref(), limit).unwrap();
+        self.more = dogs.last().map(|dog| listing.after(dog)).filter(|_| more);
+        self.ids.extend(dogs.into_iter().map(|dog| dog.id));
+    }
+
+    fn load_all(&mut self, db: &DogDB, listing: &Listing, limit: usize) {
+        while let Some(after) = self.more.take() {
+            self.load_more(db, listing, limit, Some(after));
+        }
+    }
+
+    /// Save a change to a dog, and move its row as `placed_row`'s swaps would
+    fn save(&mut self, db: &mut DogDB, listing: &Listing, dog: Dog) {
+        db.insert(dog.clone(), None).unwrap();
+
+        self.ids.retain(|id| *id != dog.id);
+        if !listing.matches(&dog) {
+            return;
+        }
+
+        let after = listing.after(&dog);
+        let (next, _) = db.page(listing, Some(&after), 1).unwrap();
+        let at = match next.first() {
+            Some(next) => self.ids.iter().position(|id| *id == next.id),
+            None => Some(self.ids.len()).filter(|_| self.more.is_none()),
+        };
+        let stopped = Some(self.ids.len()).filter(|_| self.more.as_ref() == Some(&after));
+        if let Some(at) = at.or(stopped) {
+            self.ids.insert(at, dog.id);
+        }
+    }
+}
+
+#[test]
+fn changed_dogs_are_placed_as_a_reload_would_show_them() {
+    let listings = [
+        listing(SortKey::Created, Direction::Asc),
+        listing(SortKey::Name, Direction::Asc),
+        listing(SortKey::Name, Direction::Desc),
+    ];
+    for listing in listings {
+        for mut db in databases() {
+            add_dogs(&mut db, NAMES);
+            let mut table = Table::load(&db, &listing, 3);
+
+            // The last dog shown, left where it was, then moved past the rest
+            let last = table.ids.last().unwrap();
+            let mut dog = db.find(&Uuid::parse_str(last).unwrap()).unwrap().unwrap();
+            dog.breed = "Whippet".to_string();
+            table.save(&mut db, &listing, dog.clone());
+            dog.name = "Zed".to_string();
+            table.save(&mut db, &listing, dog);
+
+            // A new dog, and one not shown yet (Dot) moved in among those that are
+            table.save(&mut db, &listing, Dog::new("Abe", "Mongrel"));
+            let mut dog = db.find(&Uuid::from_u128(7)).unwrap().unwrap();
+            dog.name = "Aa".to_string();
+            table.save(&mut db, &listing, dog);
+
+            table.load_all(&db, &listing, 3);
+            let reloaded = Table::load(&db, &listing, 100);
+            assert_eq!(table.ids, reloaded.ids, "{:?}", listing);
+        }
+    }
+}
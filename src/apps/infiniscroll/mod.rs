@@ -1,21 +1,92 @@
-use crate::components::image::Image;
+use crate::apps::App;
+use crate::components::{
+    image::Image,
+    layout::{home_nav, layout},
+};
+use crate::htmx::{full_page_fallback, FullPage};
 use axum::{
     extract::Query,
-    response::{Html, IntoResponse, Response},
+    middleware::from_fn_with_state,
+    response::{IntoResponse, Response},
     routing::get,
     Router,
 };
-use maud::html;
+use maud::{html, Markup};
 use serde::Deserialize;
 
-pub fn routes() -> Router {
-    Router::new()
-        .route("/infiniscroll", get(index))
-        .route("/infiniscroll/image-rows", get(images))
+pub struct Infiniscroll;
+
+impl App for Infiniscroll {
+    fn name(&self) -> &'static str {
+        "Infinite scroll"
+    }
+
+    fn slug(&self) -> &'static str {
+        "infiniscroll"
+    }
+
+    fn description(&self) -> &'static str {
+        "Loading more images as you reach the bottom"
+    }
+
+    fn chapter(&self) -> u8 {
+        4
+    }
+
+    fn routes(&self) -> Router {
+        let images = Router::new()
+            .route("/infiniscroll/image-rows", get(images))
+            .route_layer(from_fn_with_state(FullPage(rows_page), full_page_fallback));
+
+        Router::new()
+            .route("/infiniscroll", get(index))
+            .merge(images)
+    }
+}
+
+async fn index() -> Markup {
+    page(html! {
+        h1 { "Infinite scroll example" }
+
+        table hx-trigger="load"
+              hx-get="/infiniscroll/image-rows?page=1"
+              hx-indicator=".htmx-indicator"
+              hx-swap="beforeend" {
+            (header_row())
+        }
+        div class="htmx-indicator" {}
+    })
+}
+
+/// The image rows in a table, for when `/infiniscroll/image-rows` is loaded directly.
+/// Scrolling carries on from there.
+fn rows_page(rows: Markup) -> Markup {
+    page(html! {
+        h1 { "Infinite scroll example" }
+        table {
+            (header_row())
+            (rows)
+        }
+        div class="htmx-indicator" {}
+    })
+}
+
+fn header_row() -> Markup {
+    html! {
+        tr {
+            th { "Name" }
+            th { "Image" }
+        }
+    }
 }
 
-async fn index() -> Response {
-    Html(include_str!("../../../templates/infiniscroll.html")).into_response()
+fn page(body: Markup) -> Markup {
+    layout(
+        "Infinite Scroll",
+        &["/assets/styles.css"],
+        Some(home_nav()),
+        body,
+    )
 }
 
 const ROWS_PER_PAGE: usize = 5;
@@ -1,27 +1,89 @@
+use crate::apps::App;
+use crate::components::layout::{home_nav, layout};
+use crate::htmx::{full_page_fallback, FullPage};
+use anyhow::anyhow;
 use axum::{
-    response::{Html, IntoResponse, Response},
+    extract::State,
+    middleware::from_fn_with_state,
+    response::{IntoResponse, Response},
     routing::get,
     Router,
 };
 use maud::{html, Markup};
 use rand::seq::IndexedRandom;
+use serde::Deserialize;
+use std::sync::Arc;
 
-pub fn routes() -> Router {
-    Router::new()
-        .route("/lazy", get(index))
-        .route("/lazy/users", get(users))
+pub struct Lazy {
+    names: Arc<Names>,
 }
 
-async fn index() -> Response {
-    Html(include_str!("../../../templates/lazy.html")).into_response()
+impl Lazy {
+    pub fn new(names: Names) -> Lazy {
+        Lazy {
+            names: Arc::new(names),
+        }
+    }
+}
+
+impl App for Lazy {
+    fn name(&self) -> &'static str {
+        "Lazy loading"
+    }
+
+    fn slug(&self) -> &'static str {
+        "lazy"
+    }
+
+    fn description(&self) -> &'static str {
+        "Loading a slow table once it scrolls into view"
+    }
+
+    fn chapter(&self) -> u8 {
+        4
+    }
+
+    fn routes(&self) -> Router {
+        let users = Router::new()
+            .route("/lazy/users", get(users))
+            .route_layer(from_fn_with_state(FullPage(page), full_page_fallback));
+
+        Router::new()
+            .route("/lazy", get(index))
+            .merge(users)
+            .with_state(self.names.clone())
+    }
+}
+
+async fn index() -> Markup {
+    page(html! {
+        div class="htmx-indicator" {}
+        div hx-get="/lazy/users" hx-indicator=".htmx-indicator" hx-trigger="revealed" {}
+    })
+}
+
+/// The page, with either something to load the users, or the users themselves
+/// (for when `/lazy/users` is loaded directly).
+fn page(users: Markup) -> Markup {
+    let body = html! {
+        h1 { "Lazy loading example" }
+        div id="user-block" { (users) }
+    };
+
+    layout(
+        "Lazy example",
+        &["/assets/styles.css"],
+        Some(home_nav()),
+        body,
+    )
 }
 
-async fn users() -> Response {
+async fn users(State(names): State<Arc<Names>>) -> Response {
     // Pretend this takes a while:
     tokio::time::sleep(std::time::Duration::from_secs(1)).await;
 
     let mut rng = rand::rng();
-    let users = User::randos(&mut rng).take(8);
+    let users = User::randos(&mut rng, &names).take(8);
 
     html! {
         table {
@@ -63,14 +125,60 @@ impl User {
     }
 }
 
+/// What made-up users are made from.
+///
+/// None of the lists can be empty (see `check`).
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Names {
+    first_names: Vec<String>,
+    last_names: Vec<String>,
+
+    /// The companies are "{hype} {tech} Inc"
+    hype_terms: Vec<String>,
+    tech_terms: Vec<String>,
+}
+
+impl Default for Names {
+    fn default() -> Names {
+        let owned = |terms: &[&str]| terms.iter().map(|term| term.to_string()).collect();
+        Names {
+            first_names: owned(&FIRST_NAMES),
+            last_names: owned(&LAST_NAMES),
+            hype_terms: owned(&HYPE_TERMS),
+            tech_terms: owned(&TECH_TERMS),
+        }
+    }
+}
+
+impl Names {
+    /// There must be something to pick from in every list
+    pub fn check(&self) -> anyhow::Result<()> {
+        let lists = [
+            ("first-names", &self.first_names),
+            ("last-names", &self.last_names),
+            ("hype-terms", &self.hype_terms),
+            ("tech-terms", &self.tech_terms),
+        ];
+        match lists.iter().find(|(_, list)| list.is_empty()) {
+            Some((key, _)) => Err(anyhow!("lazy.{} can't be empty", key)),
+            None => Ok(()),
+        }
+    }
+}
+
 impl User {
-    fn randos<R: rand::Rng>(rng: &mut R) -> impl Iterator<Item = User> + '_ {
-        (0..).map(|_| Self::rando(rng))
+    fn randos<'a, R: rand::Rng>(
+        rng: &'a mut R,
+        names: &'a Names,
+    ) -> impl Iterator<Item = User> + 'a {
+        (0..).map(|_| Self::rando(rng, names))
     }
 
-    fn rando<R: rand::Rng>(rng: &mut R) -> User {
-        let first = Self::FIRST_NAMES.choose(rng).unwrap();
-        let last = Self::LAST_NAMES.choose(rng).unwrap();
+    // The unwraps are safe, as `Names::check` won't allow an empty list
+    fn rando<R: rand::Rng>(rng: &mut R, names: &Names) -> User {
+        let first = names.first_names.choose(rng).unwrap();
+        let last = names.last_names.choose(rng).unwrap();
 
         let email = format!(
             "{}.{}@example.org",
@@ -80,8 +188,8 @@ impl User {
 
         let company = format!(
             "{} {} Inc",
-            Self::HYPE_TERMS.choose(rng).unwrap(),
-            Self::TECH_TERMS.choose(rng).unwrap()
+            names.hype_terms.choose(rng).unwrap(),
+            names.tech_terms.choose(rng).unwrap()
         );
 
         User {
@@ -91,21 +199,21 @@ impl User {
             email,
         }
     }
+}
 
-    const FIRST_NAMES: [&'static str; 10] = [
-        "Alex", "Sam", "Jordan", "Élise", "Taylor", "Morgan", "Joaquín", "Riley", "Quinn", "Avery",
-    ];
+const FIRST_NAMES: [&str; 10] = [
+    "Alex", "Sam", "Jordan", "Élise", "Taylor", "Morgan", "Joaquín", "Riley", "Quinn", "Avery",
+];
 
-    const LAST_NAMES: [&'static str; 10] = [
-        "Elephant", "Penguin", "Octopus", "Kangaroo", "Platypus", "Koala", "Narwhal", "Axolotl",
-        "Sloth", "Panda",
-    ];
+const LAST_NAMES: [&str; 10] = [
+    "Elephant", "Penguin", "Octopus", "Kangaroo", "Platypus", "Koala", "Narwhal", "Axolotl",
+    "Sloth", "Panda",
+];
 
-    const HYPE_TERMS: [&'static str; 10] = [
-        "Mega", "Awesome", "Epic", "Rad", "Super", "Hyper", "Ultra", "Mighty", "Cool", "Turbo",
-    ];
+const HYPE_TERMS: [&str; 10] = [
+    "Mega", "Awesome", "Epic", "Rad", "Super", "Hyper", "Ultra", "Mighty", "Cool", "Turbo",
+];
 
-    const TECH_TERMS: [&'static str; 10] = [
-        "Byte", "Code", "Data", "Quantum", "Cyber", "Nano", "Web", "Cloud", "Net", "Tech",
-    ];
-}
+const TECH_TERMS: [&str; 10] = [
+    "Byte", "Code", "Data", "Quantum", "Cyber", "Nano", "Web", "Cloud", "Net", "Tech",
+];
@@ -1,3 +1,8 @@
+use crate::{components::layout::layout, fixtures::Fixtures};
+use axum::{routing::get, Router};
+use maud::{html, Markup};
+use std::collections::BTreeMap;
+
 pub mod dogs;
 pub mod infiniscroll;
 pub mod lazy;
@@ -6,3 +11,98 @@ pub mod pagination;
 pub mod toggle;
 pub mod trigger;
 pub mod validate;
+
+/// One of the examples from the book.
+pub trait App: Send + Sync {
+    /// Link text on the index page
+    fn name(&self) -> &'static str;
+
+    /// The app lives at `/{slug}`, with its endpoints under `/{slug}/...`
+    fn slug(&self) -> &'static str;
+
+    /// A line about what the example shows
+    fn description(&self) -> &'static str;
+
+    /// Where to find it in the book
+    fn chapter(&self) -> u8;
+
+    fn routes(&self) -> Router;
+
+    /// Called once the server has stopped, to save anything that needs saving.
+    fn shutdown(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// All the apps: adding an example means adding it here.
+pub struct Registry {
+    apps: Vec<Box<dyn App>>,
+}
+
+impl Registry {
+    pub async fn new(storage: dogs::Storage, fixtures: Fixtures) -> anyhow::Result<Registry> {
+        let apps: Vec<Box<dyn App>> = vec![
+            Box::new(dogs::Dogs::new(storage, &fixtures.dogs).await?),
+            Box::new(oob::Oob),
+            Box::new(trigger::Trigger),
+            Box::new(lazy::Lazy::new(fixtures.lazy)),
+            Box::new(validate::Validate),
+            Box::new(pagination::Pagination),
+            Box::new(infiniscroll::Infiniscroll),
+            Box::new(toggle::Toggle::new().await?),
+        ];
+
+        Ok(Registry { apps })
+    }
+
+    /// Every app's routes, plus the index page at `/`.
+    pub fn routes(&self) -> Router {
+        let index = self.index();
+
+        let router = Router::new().route("/", get(move || std::future::ready(index.clone())));
+
+        self.apps
+            .iter()
+            .fold(router, |router, app| router.merge(app.routes()))
+    }
+
+    /// Give every app the chance to tidy up, logging (rather than stopping at) failures.
+    pub fn shutdown(&self) {
+        for app in &self.apps {
+            if let Err(e) = app.shutdown() {
+                tracing::error!("{} did not shut down cleanly: {:#}", app.name(), e);
+            }
+        }
+    }
+
+    fn index(&self) -> Markup {
+        let mut chapters: BTreeMap<u8, Vec<&dyn App>> = BTreeMap::new();
+        for app in &self.apps {
+            chapters
+                .entry(app.chapter())
+                .or_default()
+                .push(app.as_ref());
+        }
+
+        let body = html! {
+            h1 { "Hello world" }
+
+            button hx-get="/version" hx-target="#version" { "Get Version from Cargo.toml" }
+            div id="version" {}
+
+            @for (chapter, apps) in chapters {
+                h2 { "Chapter " (chapter) }
+                ul {
+                    @for app in apps {
+                        li {
+                            a href={ "/" (app.slug()) } { (app.name()) }
+                            " — " (app.description())
+                        }
+                    }
+                }
+            }
+        };
+
+        layout("htmx demo", &["/assets/styles.css"], None, body)
+    }
+}
@@ -1,18 +1,55 @@
+use crate::apps::App;
+use crate::components::layout::{home_nav, layout};
 use axum::{
-    response::{Html, IntoResponse, Response},
+    response::{IntoResponse, Response},
     routing::get,
     Router,
 };
-use maud::html;
+use maud::{html, Markup};
 
-pub fn routes() -> Router {
-    Router::new()
-        .route("/oob", get(index))
-        .route("/oob/demo", get(demo))
+pub struct Oob;
+
+impl App for Oob {
+    fn name(&self) -> &'static str {
+        "Out-of-band"
+    }
+
+    fn slug(&self) -> &'static str {
+        "oob"
+    }
+
+    fn description(&self) -> &'static str {
+        "Performing out-of-band swaps (p. 24-25)"
+    }
+
+    fn chapter(&self) -> u8 {
+        3
+    }
+
+    fn routes(&self) -> Router {
+        Router::new()
+            .route("/oob", get(index))
+            .route("/oob/demo", get(demo))
+    }
 }
 
-async fn index() -> Response {
-    Html(include_str!("../../../templates/oob.html")).into_response()
+async fn index() -> Markup {
+    let body = html! {
+        h1 { "Out-of-band example" }
+
+        button hx-get="/oob/demo" hx-target="#target1" { "Send" }
+
+        div id="target1" { "original 1" }
+        div id="target2" { "original 2" }
+        div id="target3" { "original 3" }
+    };
+
+    layout(
+        "Out-of-band example",
+        &["/assets/styles.css"],
+        Some(home_nav()),
+        body,
+    )
 }
 
 async fn demo() -> Response {
@@ -1,21 +1,82 @@
-use crate::components::image::Image;
+use crate::apps::App;
+use crate::components::{
+    image::Image,
+    layout::{home_nav, layout},
+};
+use crate::htmx::{full_page_fallback, FullPage};
 use axum::{
     extract::Query,
-    response::{Html, IntoResponse, Response},
+    middleware::from_fn_with_state,
+    response::{IntoResponse, Response},
     routing::get,
     Router,
 };
-use maud::html;
+use maud::{html, Markup};
 use serde::Deserialize;
 
-pub fn routes() -> Router {
-    Router::new()
-        .route("/pagination", get(index))
-        .route("/pagination/image-rows", get(images))
+pub struct Pagination;
+
+impl App for Pagination {
+    fn name(&self) -> &'static str {
+        "Pagination"
+    }
+
+    fn slug(&self) -> &'static str {
+        "pagination"
+    }
+
+    fn description(&self) -> &'static str {
+        "Previous and next buttons over pages of images"
+    }
+
+    fn chapter(&self) -> u8 {
+        4
+    }
+
+    fn routes(&self) -> Router {
+        let images = Router::new()
+            .route("/pagination/image-rows", get(images))
+            .route_layer(from_fn_with_state(
+                FullPage(images_page),
+                full_page_fallback,
+            ));
+
+        Router::new().route("/pagination", get(index)).merge(images)
+    }
+}
+
+async fn index() -> Markup {
+    page(html! {
+        h1 { "Pagination example" }
+
+        table hx-trigger="load"
+              hx-get="/pagination/image-rows?page=1"
+              hx-indicator=".htmx-indicator" {}
+
+        div id="pagination-row" {
+            span id="pagination-buttons" {}
+            div class="htmx-indicator" {}
+        }
+    })
+}
+
+/// A page of images, for when `/pagination/image-rows` is loaded directly.
+/// The fragment brings its own table and buttons.
+fn images_page(images: Markup) -> Markup {
+    page(html! {
+        h1 { "Pagination example" }
+        (images)
+        div class="htmx-indicator" {}
+    })
 }
 
-async fn index() -> Response {
-    Html(include_str!("../../../templates/pagination.html")).into_response()
+fn page(body: Markup) -> Markup {
+    layout(
+        "Pagination",
+        &["/assets/styles.css"],
+        Some(home_nav()),
+        body,
+    )
 }
 
 const ROWS_PER_PAGE: usize = 5;
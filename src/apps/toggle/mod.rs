@@ -1,6 +1,10 @@
+use crate::apps::App;
+use crate::components::layout::{home_nav, layout};
+use crate::htmx::{full_page_fallback, FullPage};
 use axum::{
     extract::Path,
-    response::{Html, IntoResponse, Response},
+    middleware::from_fn_with_state,
+    response::{IntoResponse, Response},
     routing::get,
     Router,
 };
@@ -9,21 +13,68 @@ use maud::{html, Markup};
 
 const BUTTONS: [&str; 4] = ["Blitzen", "Prancer", "Dancer", "Vixen"];
 
-pub async fn routes() -> Router {
-    let session_config = SessionConfig::default().with_table_name("toggle");
-    let store = SessionStore::<SessionNullPool>::new(None, session_config)
-        .await
-        .unwrap();
-
-    Router::new()
-        .route("/toggle", get(index))
-        .route("/toggle/buttons", get(buttons))
-        .route("/toggle/toggle/{name}", get(toggle))
-        .layer(SessionLayer::new(store))
+pub struct Toggle {
+    store: SessionStore<SessionNullPool>,
 }
 
-async fn index() -> Response {
-    Html(include_str!("../../../templates/toggle.html")).into_response()
+impl Toggle {
+    pub async fn new() -> anyhow::Result<Toggle> {
+        let session_config = SessionConfig::default().with_table_name("toggle");
+        let store = SessionStore::<SessionNullPool>::new(None, session_config).await?;
+        Ok(Toggle { store })
+    }
+}
+
+impl App for Toggle {
+    fn name(&self) -> &'static str {
+        "Toggle"
+    }
+
+    fn slug(&self) -> &'static str {
+        "toggle"
+    }
+
+    fn description(&self) -> &'static str {
+        "Buttons that remember which one is selected, per session"
+    }
+
+    fn chapter(&self) -> u8 {
+        4
+    }
+
+    fn routes(&self) -> Router {
+        let buttons = Router::new()
+            .route("/toggle/buttons", get(buttons))
+            .route_layer(from_fn_with_state(FullPage(page), full_page_fallback));
+
+        Router::new()
+            .route("/toggle", get(index))
+            .route("/toggle/toggle/{name}", get(toggle))
+            .merge(buttons)
+            .layer(SessionLayer::new(self.store.clone()))
+    }
+}
+
+async fn index() -> Markup {
+    page(html! {
+        div hx-trigger="load" hx-get="/toggle/buttons" {}
+    })
+}
+
+/// The page, with either something to load the buttons, or the buttons themselves
+/// (for when `/toggle/buttons` is loaded directly).
+fn page(buttons: Markup) -> Markup {
+    let body = html! {
+        h1 { "Toggle example" }
+        div { (buttons) }
+    };
+
+    layout(
+        "Toggle example",
+        &["/assets/styles.css"],
+        Some(home_nav()),
+        body,
+    )
 }
 
 async fn buttons() -> Response {
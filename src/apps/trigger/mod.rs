@@ -1,57 +1,87 @@
-use axum::{
-    body::Body,
-    response::{Html, IntoResponse, Response},
-    routing::get,
-    Router,
-};
+use crate::apps::App;
+use crate::components::layout::{home_nav, layout};
+use crate::htmx::HxResponse;
+use axum::{response::IntoResponse, routing::get, Router};
+use maud::{html, Markup};
 use serde_json::json;
 
-pub fn routes() -> Router {
-    Router::new()
-        .route("/trigger", get(index))
-        .route("/trigger/event-with-no-data", get(event_with_no_data))
-        .route("/trigger/event-with-string", get(event_with_string))
-        .route("/trigger/event-with-object", get(event_with_object))
+pub struct Trigger;
+
+impl App for Trigger {
+    fn name(&self) -> &'static str {
+        "Event triggers"
+    }
+
+    fn slug(&self) -> &'static str {
+        "trigger"
+    }
+
+    fn description(&self) -> &'static str {
+        "Firing client-side events from response headers (p. 26+)"
+    }
+
+    fn chapter(&self) -> u8 {
+        3
+    }
+
+    fn routes(&self) -> Router {
+        Router::new()
+            .route("/trigger", get(index))
+            .route("/trigger/event-with-no-data", get(event_with_no_data))
+            .route("/trigger/event-with-string", get(event_with_string))
+            .route("/trigger/event-with-object", get(event_with_object))
+    }
 }
 
-async fn index() -> Response {
-    Html(include_str!("../../../templates/trigger.html")).into_response()
+async fn index() -> Markup {
+    // The events fire on the buttons and bubble up to the handlers here
+    let body = html! {
+        h1 { "Event trigger example" }
+
+        div hx-on:event1="handleEvent1(event)"
+            hx-on:event2="handleEvent2(event)"
+            hx-on:event3="handleEvent3(event)" {
+
+            button hx-get="/trigger/event-with-no-data" hx-target="#content" {
+                "Event w/ no data"
+            }
+            button hx-get="/trigger/event-with-string" hx-target="#content" {
+                "Event w/ string"
+            }
+            button hx-get="/trigger/event-with-object" hx-target="#content" {
+                "Event w/ object"
+            }
+
+            div id="content" {}
+        }
+
+        script src="/assets/trigger.js" {}
+    };
+
+    layout(
+        "Event triggering example",
+        &["/assets/styles.css"],
+        Some(home_nav()),
+        body,
+    )
 }
 
-async fn event_with_no_data() -> Response {
-    Response::builder()
-        .status(200)
-        .header("HX-Trigger", "event1")
-        .body(Body::from("dispatched event 1"))
-        .unwrap()
+async fn event_with_no_data() -> impl IntoResponse {
+    (HxResponse::new().trigger("event1"), "dispatched event 1")
 }
 
-async fn event_with_string() -> Response {
+async fn event_with_string() -> HxResponse {
     // This is delivered to the front-emd event handler for "event2" as
     // a CustomEvent with "detail" of an Object with "valie" of "some string".
 
-    let payload = json!( {
-        "event2": "some string",
-    } );
-
-    Response::builder()
-        .status(200)
-        .header("HX-Trigger", payload.to_string())
-        .body(Body::empty())
-        .unwrap()
+    HxResponse::new().trigger_with("event2", "some string")
 }
 
-async fn event_with_object() -> Response {
+async fn event_with_object() -> HxResponse {
     let payload = json!( {
-        "event3": {
-            "foo": 1,
-            "bar": 2,
-        }
+        "foo": 1,
+        "bar": 2,
     } );
 
-    Response::builder()
-        .status(200)
-        .header("HX-Trigger", payload.to_string())
-        .body(Body::empty())
-        .unwrap()
+    HxResponse::new().trigger_with("event3", payload)
 }
@@ -1,15 +1,38 @@
+use crate::apps::App;
+use crate::components::layout::{home_nav, layout};
 use axum::{
     extract::Query,
-    response::{Html, IntoResponse, Response},
+    response::{IntoResponse, Response},
     routing::get,
     Router,
 };
+use maud::{html, Markup};
 use serde::Deserialize;
 
-pub fn routes() -> Router {
-    Router::new()
-        .route("/validate", get(index))
-        .route("/validate/email-validate", get(email_validate))
+pub struct Validate;
+
+impl App for Validate {
+    fn name(&self) -> &'static str {
+        "Input validation"
+    }
+
+    fn slug(&self) -> &'static str {
+        "validate"
+    }
+
+    fn description(&self) -> &'static str {
+        "Checking an email address as you type"
+    }
+
+    fn chapter(&self) -> u8 {
+        4
+    }
+
+    fn routes(&self) -> Router {
+        Router::new()
+            .route("/validate", get(index))
+            .route("/validate/email-validate", get(email_validate))
+    }
 }
 
 #[derive(Deserialize)]
@@ -17,8 +40,29 @@ struct Params {
     email: String,
 }
 
-async fn index() -> Response {
-    Html(include_str!("../../../templates/validate.html")).into_response()
+async fn index() -> Markup {
+    let body = html! {
+        h1 { "Input validation example" }
+
+        p { "Checks for an email address being in use already." }
+        p { "Try: anything@example.org" }
+
+        form {
+            label for="email" { "Email" }
+            input id="email" name="email" type="email" autocomplete="off" size="30"
+                hx-get="/validate/email-validate"
+                hx-target="#email-error"
+                hx-trigger="keyup changed delay:200ms";
+            span class="error" id="email-error" {}
+        }
+    };
+
+    layout(
+        "Email validate example",
+        &["/assets/styles.css"],
+        Some(home_nav()),
+        body,
+    )
 }
 
 async fn email_validate(params: Query<Params>) -> Response {
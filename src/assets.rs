@@ -0,0 +1,77 @@
+use axum::{
+    extract::Path,
+    http::{
+        header::{CACHE_CONTROL, CONTENT_TYPE, ETAG, IF_NONE_MATCH},
+        HeaderMap, StatusCode,
+    },
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use rust_embed::RustEmbed;
+use tower_http::services::ServeDir;
+
+/// The `assets/` directory, compiled in so a release binary can run from anywhere.
+#[derive(RustEmbed)]
+#[folder = "assets/"]
+struct Embedded;
+
+/// Routes for `/assets`.
+///
+/// An explicit `dir` is always served from disk.
+/// Otherwise debug builds read `assets/` from disk (so live reload picks up edits),
+/// and release builds serve the copy embedded in the binary.
+pub fn routes(dir: Option<&std::path::Path>) -> Router {
+    match dir {
+        Some(dir) => Router::new().nest_service("/assets", ServeDir::new(dir)),
+        None if cfg!(debug_assertions) => {
+            Router::new().nest_service("/assets", ServeDir::new("assets"))
+        }
+        None => Router::new().route("/assets/{*path}", get(embedded)),
+    }
+}
+
+async fn embedded(Path(path): Path<String>, headers: HeaderMap) -> Response {
+    let Some(file) = Embedded::get(&path) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let hash: String = file
+        .metadata
+        .sha256_hash()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    let etag = format!("\"{}\"", hash);
+
+    // Versioned files (like htmx@2.0.1.min.js) never change.
+    // Everything else is cached for a day, then checked against the ETag.
+    let cache_control = if path.contains('@') {
+        "public, max-age=31536000, immutable"
+    } else {
+        "public, max-age=86400"
+    };
+
+    let not_modified = headers
+        .get(IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.split(',').any(|tag| tag.trim() == etag));
+
+    if not_modified {
+        return (
+            StatusCode::NOT_MODIFIED,
+            [(ETAG, etag.as_str()), (CACHE_CONTROL, cache_control)],
+        )
+            .into_response();
+    }
+
+    (
+        [
+            (CONTENT_TYPE, file.metadata.mimetype()),
+            (ETAG, etag.as_str()),
+            (CACHE_CONTROL, cache_control),
+        ],
+        file.data,
+    )
+        .into_response()
+}
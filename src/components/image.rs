@@ -1,3 +1,14 @@
+use axum::{
+    extract::Path,
+    http::{
+        header::{CACHE_CONTROL, CONTENT_TYPE},
+        StatusCode,
+    },
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use maud::{html, Markup};
 use std::ops::Range;
 
 pub struct Image {
@@ -12,19 +23,63 @@ impl Image {
 }
 
 fn make_image(i: usize) -> Image {
-    let (r, g, b) = int_to_rgb(i);
+    let name = format!("Image {}", i);
+    let url = format!("/images/{}.svg", i);
 
-    let bg_colour = format!("{:02X}{:02X}{:02X}", r, g, b);
-    let fg_colour = format!("{:02X}{:02X}{:02X}", 255 - r, 255 - g, 255 - b);
+    Image { name, url }
+}
 
-    let name = format!("Image {}", i);
+/// Serves the placeholder images, so the examples work offline.
+pub fn routes() -> Router {
+    Router::new().route("/images/{file}", get(image))
+}
 
-    let url = format!(
-        "https://placehold.co/320x240/{}/{}?text=Image+number+{}",
-        bg_colour, fg_colour, i
-    );
+async fn image(Path(file): Path<String>) -> Response {
+    let number = file
+        .strip_suffix(".svg")
+        .and_then(|n| n.parse::<usize>().ok());
 
-    Image { name, url }
+    match number {
+        // The image for a number never changes, so it can be cached forever
+        Some(n) => (
+            [
+                (CONTENT_TYPE, "image/svg+xml"),
+                (CACHE_CONTROL, "public, max-age=31536000, immutable"),
+            ],
+            svg(n),
+        )
+            .into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// A 320x240 block of colour, labelled with its number.
+fn svg(n: usize) -> Markup {
+    let (bg_colour, fg_colour) = colours(n);
+
+    html! {
+        svg xmlns="http://www.w3.org/2000/svg" width="320" height="240" viewBox="0 0 320 240" {
+            rect width="320" height="240" fill=(bg_colour) {}
+            text x="160" y="120"
+                fill=(fg_colour)
+                font-family="sans-serif"
+                font-size="24"
+                text-anchor="middle"
+                dominant-baseline="middle" {
+                "Image number " (n)
+            }
+        }
+    }
+}
+
+/// Background and (inverted) foreground colours for image `n`.
+fn colours(n: usize) -> (String, String) {
+    let (r, g, b) = int_to_rgb(n);
+
+    let bg_colour = format!("#{:02X}{:02X}{:02X}", r, g, b);
+    let fg_colour = format!("#{:02X}{:02X}{:02X}", 255 - r, 255 - g, 255 - b);
+
+    (bg_colour, fg_colour)
 }
 
 // This code below mostly from ChatGPT 4o
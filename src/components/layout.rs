@@ -0,0 +1,51 @@
+use maud::{html, Markup, DOCTYPE};
+
+/// htmx doesn't swap error responses by default, but ours are HTML meant to be seen
+/// (see `mishap::render`), so swap everything except a 204.
+const HTMX_CONFIG: &str = concat!(
+    r#"{"responseHandling": ["#,
+    r#"{"code": "204", "swap": false}, "#,
+    r#"{"code": "[23]..", "swap": true}, "#,
+    r#"{"code": "[45]..", "swap": true, "error": true}"#,
+    r#"]}"#
+);
+
+/// The page every example renders through: the `<head>`, htmx and our scripts live here,
+/// as does the `#error-toast` that errors are swapped into.
+///
+/// * `title` for the `<title>` and nothing else (pages bring their own `h1`)
+/// * `stylesheets` URLs, linked in order
+/// * `nav` shown above the body, if there is one
+/// * `body` the page content
+pub fn layout(title: &str, stylesheets: &[&str], nav: Option<Markup>, body: Markup) -> Markup {
+    html! {
+        (DOCTYPE)
+        html lang="en" {
+            head {
+                meta charset="utf-8";
+                meta name="htmx-config" content=(HTMX_CONFIG);
+                title { (title) }
+                link rel="stylesheet" href="/assets/layout.css";
+                @for stylesheet in stylesheets {
+                    link rel="stylesheet" href=(stylesheet);
+                }
+                script src="/assets/htmx@2.0.1.min.js" {}
+                script src="/assets/events.js" {}
+            }
+            body {
+                @if let Some(nav) = nav {
+                    (nav)
+                }
+                (body)
+                div id="error-toast" aria-live="polite" {}
+            }
+        }
+    }
+}
+
+/// A way back to the list of examples.
+pub fn home_nav() -> Markup {
+    html! {
+        nav { a href="/" { "All examples" } }
+    }
+}
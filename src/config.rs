@@ -0,0 +1,123 @@
+use crate::apps::dogs::Storage;
+use anyhow::Context;
+use clap::Parser;
+use serde::Deserialize;
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::PathBuf,
+    time::Duration,
+};
+
+/// Settings, taken from (in order of preference):
+/// a command line flag, an environment variable, the config file, or a default.
+pub struct Config {
+    pub address: IpAddr,
+    pub port: u16,
+
+    /// Serve `/assets` from this directory, rather than the default (see `assets::routes`)
+    pub assets: Option<PathBuf>,
+
+    /// A `tracing_subscriber::EnvFilter` directive, e.g. `info,htmx_play=debug`
+    pub log: String,
+
+    /// SQLite file for the dogs example (in memory if not set)
+    pub dogs_db: Option<PathBuf>,
+
+    /// How long to wait for in-flight requests when shutting down
+    pub drain_timeout: Duration,
+
+    /// Data for the examples to start with (see `fixtures::Fixtures`)
+    pub fixtures: Option<PathBuf>,
+}
+
+#[derive(Parser)]
+#[command(version, about = "Examples from Server-Driven Web Apps with htmx")]
+struct Args {
+    /// TOML file with any of the settings below (using the flag names as keys)
+    #[arg(long, env = "HTMX_PLAY_CONFIG")]
+    config: Option<PathBuf>,
+
+    /// Address to listen on [default: 127.0.0.1]
+    #[arg(long, env = "HTMX_PLAY_ADDRESS")]
+    address: Option<IpAddr>,
+
+    /// Port to listen on [default: 3000]
+    #[arg(long, env = "HTMX_PLAY_PORT")]
+    port: Option<u16>,
+
+    /// Directory to serve /assets from [default: ./assets when debugging, otherwise built in]
+    #[arg(long, env = "HTMX_PLAY_ASSETS")]
+    assets: Option<PathBuf>,
+
+    /// Log filter [default: info]
+    #[arg(long, env = "RUST_LOG")]
+    log: Option<String>,
+
+    /// SQLite file for the dogs example [default: keep dogs in memory]
+    #[arg(long, env = "DOGS_DB")]
+    dogs_db: Option<PathBuf>,
+
+    /// Seconds to let in-flight requests finish when shutting down [default: 10]
+    #[arg(long, env = "HTMX_PLAY_DRAIN_TIMEOUT")]
+    drain_timeout: Option<u64>,
+
+    /// TOML (or .json) file of dogs and names to start with [default: the built-in examples]
+    #[arg(long, env = "HTMX_PLAY_FIXTURES")]
+    fixtures: Option<PathBuf>,
+}
+
+/// The config file: everything is optional.
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct File {
+    address: Option<IpAddr>,
+    port: Option<u16>,
+    assets: Option<PathBuf>,
+    log: Option<String>,
+    dogs_db: Option<PathBuf>,
+    drain_timeout: Option<u64>,
+    fixtures: Option<PathBuf>,
+}
+
+impl Config {
+    /// Read the command line (exiting with usage on a bad flag), environment and config file.
+    pub fn load() -> anyhow::Result<Config> {
+        let args = Args::parse();
+
+        let file = match &args.config {
+            Some(path) => {
+                let text = std::fs::read_to_string(path)
+                    .with_context(|| format!("Unable to read config file {}", path.display()))?;
+                toml::from_str(&text)
+                    .with_context(|| format!("Unable to parse config file {}", path.display()))?
+            }
+            None => File::default(),
+        };
+
+        Ok(Config {
+            address: args
+                .address
+                .or(file.address)
+                .unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            port: args.port.or(file.port).unwrap_or(3000),
+            assets: args.assets.or(file.assets),
+            log: args.log.or(file.log).unwrap_or_else(|| "info".to_string()),
+            dogs_db: args.dogs_db.or(file.dogs_db),
+            drain_timeout: Duration::from_secs(
+                args.drain_timeout.or(file.drain_timeout).unwrap_or(10),
+            ),
+            fixtures: args.fixtures.or(file.fixtures),
+        })
+    }
+
+    pub fn socket_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.address, self.port)
+    }
+
+    pub fn dogs_storage(&self) -> Storage {
+        match &self.dogs_db {
+            Some(path) => Storage::Sqlite(path.clone()),
+            None => Storage::Memory,
+        }
+    }
+}
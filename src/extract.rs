@@ -0,0 +1,30 @@
+use crate::mishap::{Kind, Mishap};
+use axum::{extract::FromRequestParts, http::request::Parts};
+use serde::de::DeserializeOwned;
+
+/// axum's `Path`, but a path that doesn't parse (say, a malformed id) is a `Mishap`,
+/// so it gets a 400 with an HTML body that htmx can show.
+pub struct Path<T>(pub T);
+
+impl<S, T> FromRequestParts<S> for Path<T>
+where
+    T: DeserializeOwned + Send,
+    S: Send + Sync,
+{
+    type Rejection = Mishap;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        match axum::extract::Path::<T>::from_request_parts(parts, state).await {
+            Ok(axum::extract::Path(value)) => Ok(Path(value)),
+            Err(rejection) => {
+                // A route that doesn't match its handler is our bug, not the caller's
+                let kind = if rejection.status().is_server_error() {
+                    Kind::Internal
+                } else {
+                    Kind::Validation
+                };
+                Err(Mishap::new(kind, anyhow::Error::msg(rejection.body_text())))
+            }
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use crate::apps::{dogs, lazy};
+use anyhow::{anyhow, Context};
+use serde::Deserialize;
+use std::path::Path;
+
+/// The data the examples start with.
+///
+/// Read from a TOML (or `.json`) file given with `--fixtures`, e.g.
+///
+/// ```toml
+/// [[dogs]]
+/// name = "Comet"
+/// breed = "Whippet"
+///
+/// [lazy]
+/// first-names = ["Alex", "Sam"]
+/// ```
+///
+/// Anything left out keeps the built-in data.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Fixtures {
+    /// Added to a new dogs database (but not to one that already exists)
+    pub dogs: dogs::Seeds,
+
+    /// Where the lazy loading example gets its made-up users
+    pub lazy: lazy::Names,
+}
+
+impl Fixtures {
+    /// The fixtures in `path`, or the built-in ones.
+    pub fn load(path: Option<&Path>) -> anyhow::Result<Fixtures> {
+        let Some(path) = path else {
+            return Ok(Fixtures::default());
+        };
+
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Unable to read fixtures {}", path.display()))?;
+
+        let is_json = path.extension().is_some_and(|ext| ext == "json");
+        let fixtures: Fixtures = if is_json {
+            serde_json::from_str(&text).map_err(anyhow::Error::from)
+        } else {
+            toml::from_str(&text).map_err(anyhow::Error::from)
+        }
+        .with_context(|| format!("Unable to parse fixtures {}", path.display()))?;
+
+        fixtures
+            .lazy
+            .check()
+            .map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+
+        Ok(fixtures)
+    }
+}
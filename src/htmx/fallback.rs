@@ -0,0 +1,59 @@
+use super::HxRequest;
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::header::{CONTENT_LENGTH, CONTENT_TYPE},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use maud::{Markup, PreEscaped};
+
+/// How an app embeds one of its fragments into a complete page.
+#[derive(Clone, Copy)]
+pub struct FullPage(pub fn(Markup) -> Markup);
+
+/// Middleware for routes that return HTML fragments.
+///
+/// htmx requests get the fragment as usual.
+/// Anything else (following a link, a hard refresh, history restoration)
+/// gets the fragment wrapped up in the app's page,
+/// so fragment URLs are safe to link to and to push into history.
+///
+/// Use with `middleware::from_fn_with_state(FullPage(some_page), full_page_fallback)`.
+pub async fn full_page_fallback(
+    State(FullPage(page)): State<FullPage>,
+    hx: HxRequest,
+    request: Request,
+    next: Next,
+) -> Response {
+    let response = next.run(request).await;
+
+    if hx.request && !hx.history_restore_request {
+        return response;
+    }
+
+    let is_html = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/html"));
+
+    if !response.status().is_success() || !is_html {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+
+    let fragment = match to_bytes(body, usize::MAX).await {
+        Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+        Err(e) => {
+            tracing::error!("Unable to read fragment: {}", e);
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+
+    // The body is about to change size
+    parts.headers.remove(CONTENT_LENGTH);
+
+    (parts, page(PreEscaped(fragment))).into_response()
+}
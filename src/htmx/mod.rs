@@ -0,0 +1,11 @@
+//! Typed access to the headers htmx sends and understands.
+//!
+//! See <https://htmx.org/reference/#headers>
+
+mod fallback;
+mod request;
+mod response;
+
+pub use fallback::{full_page_fallback, FullPage};
+pub use request::HxRequest;
+pub use response::HxResponse;
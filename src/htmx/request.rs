@@ -0,0 +1,73 @@
+use axum::{extract::FromRequestParts, http::request::Parts, http::HeaderMap};
+use std::convert::Infallible;
+
+/// The request headers htmx sends.
+///
+/// Use it as an extractor to tell htmx requests apart from ordinary page loads,
+/// or build one from a `HeaderMap` with `HxRequest::from_headers`.
+#[derive(Clone, Debug, Default)]
+pub struct HxRequest {
+    /// HX-Request: the request was made by htmx
+    pub request: bool,
+
+    /// HX-Boosted: via an element using `hx-boost`
+    // Like the other `allow`s here, for a header none of the examples use yet
+    #[allow(dead_code)]
+    pub boosted: bool,
+
+    /// HX-Current-URL: the browser's URL at the time
+    pub current_url: Option<String>,
+
+    /// HX-History-Restore-Request: htmx wants a full page to restore history
+    pub history_restore_request: bool,
+
+    /// HX-Prompt: the user's answer to an `hx-prompt`
+    #[allow(dead_code)]
+    pub prompt: Option<String>,
+
+    /// HX-Target: id of the target element, if it has one
+    pub target: Option<String>,
+
+    /// HX-Trigger: id of the triggering element, if it has one
+    #[allow(dead_code)]
+    pub trigger: Option<String>,
+
+    /// HX-Trigger-Name: name of the triggering element, if it has one
+    #[allow(dead_code)]
+    pub trigger_name: Option<String>,
+}
+
+impl HxRequest {
+    pub fn from_headers(headers: &HeaderMap) -> HxRequest {
+        // Browsers send the prompt text as-is, so don't insist on ASCII
+        let text = |name: &str| {
+            headers
+                .get(name)
+                .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned())
+        };
+
+        let flag = |name: &str| text(name).is_some_and(|value| value == "true");
+
+        HxRequest {
+            request: flag("hx-request"),
+            boosted: flag("hx-boosted"),
+            current_url: text("hx-current-url"),
+            history_restore_request: flag("hx-history-restore-request"),
+            prompt: text("hx-prompt"),
+            target: text("hx-target"),
+            trigger: text("hx-trigger"),
+            trigger_name: text("hx-trigger-name"),
+        }
+    }
+}
+
+impl<S> FromRequestParts<S> for HxRequest
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(HxRequest::from_headers(&parts.headers))
+    }
+}
@@ -0,0 +1,312 @@
+use crate::mishap::Mishap;
+use anyhow::anyhow;
+use axum::{
+    http::{HeaderName, HeaderValue},
+    response::{IntoResponse, IntoResponseParts, Response, ResponseParts},
+};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+const HX_TRIGGER: HeaderName = HeaderName::from_static("hx-trigger");
+const HX_TRIGGER_AFTER_SWAP: HeaderName = HeaderName::from_static("hx-trigger-after-swap");
+const HX_TRIGGER_AFTER_SETTLE: HeaderName = HeaderName::from_static("hx-trigger-after-settle");
+const HX_LOCATION: HeaderName = HeaderName::from_static("hx-location");
+const HX_PUSH_URL: HeaderName = HeaderName::from_static("hx-push-url");
+const HX_REPLACE_URL: HeaderName = HeaderName::from_static("hx-replace-url");
+const HX_REDIRECT: HeaderName = HeaderName::from_static("hx-redirect");
+const HX_REFRESH: HeaderName = HeaderName::from_static("hx-refresh");
+const HX_RESWAP: HeaderName = HeaderName::from_static("hx-reswap");
+const HX_RETARGET: HeaderName = HeaderName::from_static("hx-retarget");
+const HX_RESELECT: HeaderName = HeaderName::from_static("hx-reselect");
+
+/// Response headers that tell htmx what to do next.
+///
+/// Build one up and return it alongside a body, e.g.
+/// `(HxResponse::new().trigger("selection-change"), markup)`,
+/// or on its own for an empty response.
+#[derive(Default)]
+pub struct HxResponse {
+    trigger: Events,
+    trigger_after_swap: Events,
+    trigger_after_settle: Events,
+    location: Option<String>,
+    push_url: Option<String>,
+    replace_url: Option<String>,
+    redirect: Option<String>,
+    refresh: bool,
+    reswap: Option<String>,
+    retarget: Option<String>,
+    reselect: Option<String>,
+}
+
+impl HxResponse {
+    pub fn new() -> HxResponse {
+        HxResponse::default()
+    }
+
+    /// Fire `event` on the client as soon as the response is received.
+    pub fn trigger(mut self, event: &str) -> HxResponse {
+        self.trigger.add(event, Ok(Value::Null));
+        self
+    }
+
+    /// Fire `event` with `detail` (serialised to JSON) as the event's detail.
+    pub fn trigger_with<T: Serialize>(mut self, event: &str, detail: T) -> HxResponse {
+        self.trigger.add(event, serde_json::to_value(detail));
+        self
+    }
+
+    /// As `trigger`, but after htmx has swapped in the content.
+    // Like the other `allow`s here, for a header none of the examples use yet
+    #[allow(dead_code)]
+    pub fn trigger_after_swap(mut self, event: &str) -> HxResponse {
+        self.trigger_after_swap.add(event, Ok(Value::Null));
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn trigger_after_swap_with<T: Serialize>(mut self, event: &str, detail: T) -> HxResponse {
+        self.trigger_after_swap
+            .add(event, serde_json::to_value(detail));
+        self
+    }
+
+    /// As `trigger`, but after the settle step.
+    #[allow(dead_code)]
+    pub fn trigger_after_settle(mut self, event: &str) -> HxResponse {
+        self.trigger_after_settle.add(event, Ok(Value::Null));
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn trigger_after_settle_with<T: Serialize>(mut self, event: &str, detail: T) -> HxResponse {
+        self.trigger_after_settle
+            .add(event, serde_json::to_value(detail));
+        self
+    }
+
+    /// Client-side redirect without a full page reload (like following an `hx-boost` link).
+    #[allow(dead_code)]
+    pub fn location(mut self, path: impl Into<String>) -> HxResponse {
+        self.location = Some(path.into());
+        self
+    }
+
+    /// Push a new URL into the browser's history.
+    #[allow(dead_code)]
+    pub fn push_url(mut self, url: impl Into<String>) -> HxResponse {
+        self.push_url = Some(url.into());
+        self
+    }
+
+    /// Replace the current URL in the location bar, without adding to history.
+    pub fn replace_url(mut self, url: impl Into<String>) -> HxResponse {
+        self.replace_url = Some(url.into());
+        self
+    }
+
+    /// Full page redirect.
+    #[allow(dead_code)]
+    pub fn redirect(mut self, url: impl Into<String>) -> HxResponse {
+        self.redirect = Some(url.into());
+        self
+    }
+
+    /// Full page refresh.
+    #[allow(dead_code)]
+    pub fn refresh(mut self) -> HxResponse {
+        self.refresh = true;
+        self
+    }
+
+    /// Override the `hx-swap` of the element that made the request, e.g. `"outerHTML"`.
+    pub fn reswap(mut self, swap: impl Into<String>) -> HxResponse {
+        self.reswap = Some(swap.into());
+        self
+    }
+
+    /// Swap into the element matching this selector instead of the request's target.
+    pub fn retarget(mut self, selector: impl Into<String>) -> HxResponse {
+        self.retarget = Some(selector.into());
+        self
+    }
+
+    /// Only swap in the part of the response matching this selector.
+    #[allow(dead_code)]
+    pub fn reselect(mut self, selector: impl Into<String>) -> HxResponse {
+        self.reselect = Some(selector.into());
+        self
+    }
+}
+
+impl IntoResponseParts for HxResponse {
+    type Error = Mishap;
+
+    fn into_response_parts(self, mut res: ResponseParts) -> Result<ResponseParts, Mishap> {
+        let headers = res.headers_mut();
+
+        let events = [
+            (HX_TRIGGER, self.trigger),
+            (HX_TRIGGER_AFTER_SWAP, self.trigger_after_swap),
+            (HX_TRIGGER_AFTER_SETTLE, self.trigger_after_settle),
+        ];
+
+        for (name, events) in events {
+            if let Some(value) = events.header_value()? {
+                headers.insert(name, value);
+            }
+        }
+
+        let refresh = Some("true".to_string()).filter(|_| self.refresh);
+
+        let values = [
+            (HX_LOCATION, self.location),
+            (HX_PUSH_URL, self.push_url),
+            (HX_REPLACE_URL, self.replace_url),
+            (HX_REDIRECT, self.redirect),
+            (HX_REFRESH, refresh),
+            (HX_RESWAP, self.reswap),
+            (HX_RETARGET, self.retarget),
+            (HX_RESELECT, self.reselect),
+        ];
+
+        for (name, value) in values {
+            if let Some(value) = value {
+                let value = HeaderValue::try_from(value)
+                    .map_err(|e| Mishap::internal(anyhow!("Bad {} header: {}", name, e)))?;
+                headers.insert(name, value);
+            }
+        }
+
+        Ok(res)
+    }
+}
+
+impl IntoResponse for HxResponse {
+    fn into_response(self) -> Response {
+        (self, ()).into_response()
+    }
+}
+
+/// The events for one of the HX-Trigger headers.
+#[derive(Default)]
+struct Events {
+    // Null for an event without a detail
+    events: Vec<(String, Value)>,
+
+    // Serialisation problems are held until we build the header
+    error: Option<serde_json::Error>,
+}
+
+impl Events {
+    fn add(&mut self, event: &str, detail: serde_json::Result<Value>) {
+        match detail {
+            Ok(detail) => self.events.push((event.to_string(), detail)),
+            Err(e) => self.error = Some(e),
+        }
+    }
+
+    /// Plain event names are sent as a comma-separated list.
+    /// If any event has a detail, everything is merged into a single JSON object,
+    /// keyed by event name.
+    fn header_value(self) -> Result<Option<HeaderValue>, Mishap> {
+        if let Some(e) = self.error {
+            return Err(Mishap::internal(anyhow!(
+                "Unable to serialise event detail: {}",
+                e
+            )));
+        }
+
+        if self.events.is_empty() {
+            return Ok(None);
+        }
+
+        let value = if self.events.iter().all(|(_, detail)| detail.is_null()) {
+            let names: Vec<String> = self.events.into_iter().map(|(name, _)| name).collect();
+            names.join(", ")
+        } else {
+            let merged: Map<String, Value> = self.events.into_iter().collect();
+            ascii_only(&Value::Object(merged).to_string())
+        };
+
+        let value = HeaderValue::try_from(value)
+            .map_err(|e| Mishap::internal(anyhow!("Bad event header: {}", e)))?;
+
+        Ok(Some(value))
+    }
+}
+
+/// Header values must be ASCII, so escape anything else in the JSON as `\uXXXX`.
+/// (JSON only allows non-ASCII inside strings, where the escape means the same thing.)
+fn ascii_only(json: &str) -> String {
+    let mut escaped = String::with_capacity(json.len());
+    for c in json.chars() {
+        if c.is_ascii() {
+            escaped.push(c);
+        } else {
+            let mut units = [0; 2];
+            for unit in c.encode_utf16(&mut units) {
+                escaped.push_str(&format!("\\u{:04x}", unit));
+            }
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ascii_only, Events};
+    use serde_json::{json, Value};
+
+    fn events(events: &[(&str, Value)]) -> Events {
+        let mut all = Events::default();
+        for (event, detail) in events {
+            all.add(event, Ok(detail.clone()));
+        }
+        all
+    }
+
+    fn header(events: Events) -> Option<String> {
+        match events.header_value() {
+            Ok(value) => value.map(|value| value.to_str().unwrap().to_string()),
+            Err(mishap) => panic!("{:#}", mishap.error),
+        }
+    }
+
+    #[test]
+    fn joins_plain_names() {
+        let plain = events(&[("selection-change", Value::Null), ("saved", Value::Null)]);
+        assert_eq!(header(plain).unwrap(), "selection-change, saved");
+    }
+
+    #[test]
+    fn merges_into_json_when_any_has_a_detail() {
+        let mixed = events(&[
+            ("selection-change", Value::Null),
+            ("saved", json!({ "id": 7 })),
+        ]);
+        let merged: Value = serde_json::from_str(&header(mixed).unwrap()).unwrap();
+        assert_eq!(
+            merged,
+            json!({ "selection-change": null, "saved": { "id": 7 } })
+        );
+    }
+
+    #[test]
+    fn no_header_without_events() {
+        assert_eq!(header(Events::default()), None);
+    }
+
+    #[test]
+    fn escapes_anything_not_ascii() {
+        // "🐕" is outside the Basic Multilingual Plane, so it's a surrogate pair in UTF-16
+        assert_eq!(
+            ascii_only(r#"{"a":"Café 🐕"}"#),
+            r#"{"a":"Caf\u00e9 \ud83d\udc15"}"#
+        );
+
+        let detail = events(&[("saved", json!("Café 🐕"))]);
+        let merged: Value = serde_json::from_str(&header(detail).unwrap()).unwrap();
+        assert_eq!(merged, json!({ "saved": "Café 🐕" }));
+    }
+}
@@ -1,54 +1,92 @@
-use axum::{
-    http::Request,
-    response::{Html, IntoResponse, Response},
-    routing::get,
-    Router,
-};
-use tower_http::services::ServeDir;
+use anyhow::Context;
+use axum::{middleware, routing::get, Router};
+use config::Config;
+use tracing_subscriber::EnvFilter;
 
 mod apps;
+mod assets;
 mod components;
+mod config;
+mod extract;
+mod fixtures;
+mod htmx;
 mod mishap;
+mod shutdown;
+mod validation;
 
 #[tokio::main]
-async fn main() {
-    tracing_subscriber::fmt::init();
+async fn main() -> anyhow::Result<()> {
+    let config = Config::load()?;
 
-    let assets = Router::new().nest_service("/assets", ServeDir::new("assets"));
+    let filter = EnvFilter::try_new(&config.log)
+        .with_context(|| format!("Bad log filter: {}", config.log))?;
+    tracing_subscriber::fmt().with_env_filter(filter).init();
+
+    let assets = assets::routes(config.assets.as_deref());
+
+    let fixtures = fixtures::Fixtures::load(config.fixtures.as_deref())?;
+    let registry = apps::Registry::new(config.dogs_storage(), fixtures).await?;
 
     let routes = Router::new()
-        .route("/", get(root))
         .route("/version", get(version))
-        .merge(apps::dogs::routes())
-        .merge(apps::oob::routes())
-        .merge(apps::trigger::routes())
-        .merge(apps::lazy::routes())
-        .merge(apps::validate::routes())
-        .merge(apps::pagination::routes())
-        .merge(apps::infiniscroll::routes())
-        .merge(apps::toggle::routes().await);
+        .merge(components::image::routes())
+        .merge(registry.routes());
 
-    let app = Router::new().merge(assets).merge(routes);
+    let app = Router::new()
+        .merge(assets)
+        .merge(routes)
+        .layer(middleware::from_fn(mishap::render));
 
     // During development we want live-reload, but not of the htmx snippets
 
     #[cfg(debug_assertions)]
-    fn not_htmx<Body>(req: &Request<Body>) -> bool {
-        !req.headers().contains_key("hx-request")
+    fn not_htmx<Body>(req: &axum::http::Request<Body>) -> bool {
+        !htmx::HxRequest::from_headers(req.headers()).request
     }
 
     #[cfg(debug_assertions)]
     let app = app.layer(tower_livereload::LiveReloadLayer::new().request_predicate(not_htmx));
 
-    let listener = tokio::net::TcpListener::bind("127.0.0.1:3000")
+    let addr = config.socket_addr();
+    let listener = tokio::net::TcpListener::bind(addr)
         .await
-        .unwrap();
-    tracing::debug!("listening on {}", listener.local_addr().unwrap());
-    axum::serve(listener, app).await.unwrap();
+        .with_context(|| format!("Unable to listen on {}", addr))?;
+    tracing::info!("listening on http://{}", addr);
+
+    // On a signal we stop accepting connections and give in-flight requests time to finish
+    let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+    let mut server = tokio::spawn(async move {
+        axum::serve(listener, app)
+            .with_graceful_shutdown(async {
+                let _ = stopped.await;
+            })
+            .await
+    });
+
+    // A server error still gets the stores flushed, and is returned after
+    let served = tokio::select! {
+        result = &mut server => served(result),
+        _ = shutdown::signal() => {
+            tracing::info!("shutting down: draining requests for up to {:?}", config.drain_timeout);
+            let _ = stop.send(());
+            match tokio::time::timeout(config.drain_timeout, server).await {
+                Ok(result) => served(result),
+                Err(_) => {
+                    tracing::warn!("requests still running after {:?}", config.drain_timeout);
+                    Ok(())
+                }
+            }
+        }
+    };
+
+    registry.shutdown();
+    tracing::info!("stopped");
+    served
 }
 
-async fn root() -> Response {
-    Html(include_str!("../templates/index.html")).into_response()
+/// How the server task ended: an error from the server, or the task panicking
+fn served(result: Result<std::io::Result<()>, tokio::task::JoinError>) -> anyhow::Result<()> {
+    Ok(result??)
 }
 
 async fn version() -> &'static str {
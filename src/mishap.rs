@@ -1,19 +1,112 @@
+use crate::components::layout::{home_nav, layout};
+use crate::htmx::{HxRequest, HxResponse};
 use axum::{
-    http::StatusCode,
+    body::to_bytes,
+    extract::Request,
+    http::{
+        header::{ACCEPT, CONTENT_TYPE},
+        HeaderMap, StatusCode,
+    },
+    middleware::Next,
     response::{IntoResponse, Response},
+    Json,
 };
+use maud::{html, Markup};
+use serde_json::json;
 
 // Thank you: https://github.com/tokio-rs/axum/blob/main/examples/anyhow-error-response/src/main.rs
 
-pub struct Mishap(pub anyhow::Error);
+/// What sort of thing went wrong, which decides the status code.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    NotFound,
+    Validation,
+    Conflict,
+    Internal,
+}
+
+impl Kind {
+    pub fn status(self) -> StatusCode {
+        match self {
+            Kind::NotFound => StatusCode::NOT_FOUND,
+            Kind::Validation => StatusCode::BAD_REQUEST,
+            Kind::Conflict => StatusCode::CONFLICT,
+            Kind::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn from_status(status: StatusCode) -> Kind {
+        match status {
+            StatusCode::NOT_FOUND => Kind::NotFound,
+            StatusCode::CONFLICT => Kind::Conflict,
+            s if s.is_client_error() => Kind::Validation,
+            _ => Kind::Internal,
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            Kind::NotFound => "Not found",
+            Kind::Validation => "Invalid request",
+            Kind::Conflict => "Conflict",
+            Kind::Internal => "Something went wrong",
+        }
+    }
+}
+
+pub struct Mishap {
+    pub kind: Kind,
+    pub error: anyhow::Error,
+}
+
+impl Mishap {
+    pub fn new(kind: Kind, error: impl Into<anyhow::Error>) -> Mishap {
+        Mishap {
+            kind,
+            error: error.into(),
+        }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Mishap {
+        Mishap::new(Kind::NotFound, anyhow::Error::msg(message.into()))
+    }
+
+    pub fn validation(error: impl Into<anyhow::Error>) -> Mishap {
+        Mishap::new(Kind::Validation, error)
+    }
+
+    pub fn internal(error: impl Into<anyhow::Error>) -> Mishap {
+        Mishap::new(Kind::Internal, error)
+    }
+}
+
+/// Left on the response for `render` to turn into HTML.
+#[derive(Clone)]
+struct Report {
+    kind: Kind,
+    message: String,
+}
 
 impl IntoResponse for Mishap {
     fn into_response(self) -> Response {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Something went wrong: {}", self.0),
+        if self.kind == Kind::Internal {
+            tracing::error!("{:#}", self.error);
+        }
+
+        let report = Report {
+            kind: self.kind,
+            message: format!("{:#}", self.error),
+        };
+
+        // Plain text, in case nothing renders the report
+        let mut response = (
+            self.kind.status(),
+            format!("{}: {}", report.kind.title(), report.message),
         )
-            .into_response()
+            .into_response();
+
+        response.extensions_mut().insert(report);
+        response
     }
 }
 
@@ -22,6 +115,110 @@ where
     E: Into<anyhow::Error>,
 {
     fn from(err: E) -> Self {
-        Self(err.into())
+        Mishap::internal(err)
+    }
+}
+
+/// Left on a response by `json_errors`.
+#[derive(Clone, Copy)]
+struct JsonOnly;
+
+/// Middleware for routes which only speak JSON (such as an API),
+/// so `render` gives their errors as JSON whatever the request accepts.
+pub async fn json_errors(request: Request, next: Next) -> Response {
+    let mut response = next.run(request).await;
+    response.extensions_mut().insert(JsonOnly);
+    response
+}
+
+/// Middleware to turn errors into something a person will see.
+///
+/// htmx requests get a fragment retargeted into the page's `#error-toast`.
+/// Clients which accept JSON (such as API scripts), and routes under `json_errors`,
+/// get `{"error": ..., "message": ...}`.
+/// Everything else gets a full error page.
+/// This covers `Mishap`s, and also any other error that isn't already HTML or JSON
+/// (such as axum's plain-text extractor rejections, or a 404 for an unknown route).
+pub async fn render(hx: HxRequest, request: Request, next: Next) -> Response {
+    let wants_json = accepts_json(request.headers());
+    let response = next.run(request).await;
+    let json_only = response.extensions().get::<JsonOnly>().is_some();
+
+    let status = response.status();
+    if !status.is_client_error() && !status.is_server_error() {
+        return response;
+    }
+
+    let report = match response.extensions().get::<Report>() {
+        Some(report) => report.clone(),
+        None => {
+            let rendered = response
+                .headers()
+                .get(CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|value| {
+                    value.starts_with("text/html") || value.starts_with("application/json")
+                });
+
+            if rendered {
+                return response;
+            }
+
+            let body = to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap_or_default();
+
+            Report {
+                kind: Kind::from_status(status),
+                message: String::from_utf8_lossy(&body).into_owned(),
+            }
+        }
+    };
+
+    if json_only || (wants_json && !hx.request) {
+        let body = json!({ "error": report.kind.title(), "message": report.message });
+        (status, Json(body)).into_response()
+    } else if hx.request && !hx.history_restore_request {
+        let hx = HxResponse::new()
+            .retarget("#error-toast")
+            .reswap("innerHTML");
+        (status, hx, toast(&report)).into_response()
+    } else {
+        (status, page(&report)).into_response()
     }
 }
+
+fn accepts_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.contains("application/json"))
+}
+
+fn toast(report: &Report) -> Markup {
+    html! {
+        div class="toast" {
+            strong { (report.kind.title()) }
+            @if !report.message.is_empty() {
+                " " (report.message)
+            }
+            button type="button" hx-on:click="this.parentElement.remove()" { "Dismiss" }
+        }
+    }
+}
+
+fn page(report: &Report) -> Markup {
+    let body = html! {
+        h1 { (report.kind.title()) }
+        @if !report.message.is_empty() {
+            p { (report.message) }
+        }
+    };
+
+    layout(
+        report.kind.title(),
+        &["/assets/styles.css"],
+        Some(home_nav()),
+        body,
+    )
+}
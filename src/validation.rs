@@ -0,0 +1,78 @@
+use serde::Serialize;
+use std::{collections::BTreeMap, fmt};
+
+/// A check on a (trimmed) form value.
+#[derive(Clone, Copy)]
+pub enum Rule {
+    /// Something other than whitespace
+    Required,
+
+    /// No more than this many characters
+    MaxChars(usize),
+
+    /// Every character passes the test, described for the user
+    /// e.g. `Chars(char::is_alphanumeric, "letters and numbers")`
+    Chars(fn(char) -> bool, &'static str),
+}
+
+impl Rule {
+    fn check(&self, value: &str) -> Option<String> {
+        match self {
+            Rule::Required if value.is_empty() => Some("Required".to_string()),
+            Rule::MaxChars(max) if value.chars().count() > *max => {
+                Some(format!("No more than {} characters", max))
+            }
+            Rule::Chars(allowed, description) if !value.chars().all(allowed) => {
+                Some(format!("Only {}", description))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Problems found with a form, by field name.
+///
+/// Serializes as an object of field name to messages.
+#[derive(Default, Debug, Serialize)]
+#[serde(transparent)]
+pub struct Errors {
+    fields: BTreeMap<&'static str, Vec<String>>,
+}
+
+impl Errors {
+    /// Apply `rules` to `value` (after trimming), recording any problems against `field`.
+    pub fn check(&mut self, field: &'static str, value: &str, rules: &[Rule]) {
+        let value = value.trim();
+        for rule in rules {
+            if let Some(message) = rule.check(value) {
+                self.fields.entry(field).or_default().push(message);
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// The problems with one field (if any).
+    pub fn field(&self, field: &str) -> &[String] {
+        self.fields
+            .get(field)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+}
+
+/// e.g. `name: Required; breed: No more than 100 characters`
+impl fmt::Display for Errors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut separator = "";
+        for (field, messages) in &self.fields {
+            for message in messages {
+                write!(f, "{}{}: {}", separator, field, message)?;
+                separator = "; ";
+            }
+        }
+        Ok(())
+    }
+}